@@ -0,0 +1,259 @@
+//! The game playing itself behind the main menu once nobody has touched anything for a while.
+//!
+//! The demo is a real game in `GameState::Level`, so the gameplay systems run just as they do
+//! for the player, with a see-through copy of the main menu drawn over it. The AI plays a random
+//! campaign level, or a saved replay is played back. The demo is not recorded and its score is
+//! thrown away, and any key, click or mouse movement goes back to the normal menu.
+
+use bevy::{input::keyboard::KeyboardInput, prelude::*};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::autopilot::{AiController, AiDifficulty, Autopilot};
+use crate::controls::{ActiveController, PlayerInput};
+use crate::events::LevelCompleted;
+use crate::gameplay::{GameplaySystem, Score};
+use crate::level::Level;
+use crate::replay::{Playback, Recorder};
+use crate::sprite_sheet::{GameSpriteSheet, SpriteSheet};
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedBackdrop};
+use crate::transition::{TransitionStyle, Transitions};
+use crate::BlocksConfig;
+
+/// Seconds the main menu has to sit idle before the demo starts.
+pub const IDLE_SECONDS: f32 = 20.0;
+
+/// How much of the menu's backdrop covers the demo.
+const OVERLAY_ALPHA: f32 = 0.6;
+
+/// How the AI plays the demo; good, but not flawless.
+const DEMO_DIFFICULTY: AiDifficulty = AiDifficulty::NORMAL;
+
+/// The idle time on the main menu, and whether the demo is playing.
+#[derive(Default)]
+pub struct Attract {
+    idle: f32,
+    playing: bool,
+    /// The demo is on its way back to the menu.
+    leaving: bool,
+}
+
+impl Attract {
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Counts `seconds` of idling towards the demo, or starts over after some `input`. Returns
+    /// whether the menu has now been idle long enough.
+    pub fn tick(&mut self, seconds: f32, input: bool) -> bool {
+        if input {
+            self.idle = 0.0;
+            return false;
+        }
+        self.idle += seconds;
+        self.idle >= IDLE_SECONDS
+    }
+}
+
+/// Whether the player pressed a key or mouse button, or moved the mouse, this frame.
+fn any_input(
+    keyboard_events: &mut EventReader<KeyboardInput>,
+    mouse_buttons: &Input<MouseButton>,
+    cursor_events: &mut EventReader<CursorMoved>,
+) -> bool {
+    // every event has to be read, or it would still count on the next frame
+    let key = keyboard_events.iter().count() > 0;
+    let cursor = cursor_events.iter().count() > 0;
+    key || cursor || mouse_buttons.get_just_pressed().next().is_some()
+}
+
+/// Ends the demo when the main menu is back, and starts counting idle time again.
+pub fn menu_enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::MainMenu).with_system(finish_demo_system)
+}
+
+pub fn menu_update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::MainMenu).with_system(wait_for_idle_system)
+}
+
+/// Draws the menu over the demo and keeps the demo out of the recordings.
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Level)
+        .with_system(setup_demo_system.after(GameplaySystem::Setup))
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Level)
+        .with_system(next_level_system.after(GameplaySystem::Progress))
+}
+
+/// A demo that wins the whole campaign goes straight back to the menu.
+pub fn game_over_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::GameOver).with_system(skip_game_over_system)
+}
+
+/// Starts the demo once the main menu has been left alone for [`IDLE_SECONDS`].
+#[allow(clippy::too_many_arguments)]
+fn wait_for_idle_system(
+    time: Res<Time>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut cursor_events: EventReader<CursorMoved>,
+    mut attract: ResMut<Attract>,
+    mut transitions: ResMut<Transitions>,
+    mut playback: ResMut<Playback>,
+    mut score: ResMut<Score>,
+    mut controller: ResMut<ActiveController>,
+    recorder: Res<Recorder>,
+    levels: Res<Vec<Level>>,
+    config: Res<BlocksConfig>,
+    sprite_sheet: Res<GameSpriteSheet>,
+    sheets: Res<Assets<SpriteSheet>>,
+) {
+    let input = any_input(&mut keyboard_events, &mouse_buttons, &mut cursor_events);
+    if transitions.is_busy() || !attract.tick(time.delta_seconds(), input) {
+        return;
+    }
+    // the level is built from the sprite sheet, so wait for it to load
+    if levels.is_empty() || !sheets.contains(&sprite_sheet.0) {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let replays: Vec<_> = recorder
+        .save_to
+        .as_deref()
+        .map(crate::replay::list)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, replay)| replay)
+        .filter(|replay| replay.check(&levels).is_ok())
+        .collect();
+    match replays.choose(&mut rng) {
+        Some(replay) if rng.gen_bool(0.5) => playback.start(replay.clone()),
+        _ => {
+            *score = Score::with_lives(config.starting_lives);
+            score.current_level = rng.gen_range(0..levels.len());
+            controller.0 = Box::new(AiController::new(DEMO_DIFFICULTY, None));
+        }
+    }
+    attract.playing = true;
+    transitions.set(GameState::Level, TransitionStyle::Fade);
+}
+
+fn finish_demo_system(
+    mut attract: ResMut<Attract>,
+    mut score: ResMut<Score>,
+    mut controller: ResMut<ActiveController>,
+    autopilot: Res<Autopilot>,
+    config: Res<BlocksConfig>,
+) {
+    attract.idle = 0.0;
+    if !attract.playing {
+        return;
+    }
+    attract.playing = false;
+    attract.leaving = false;
+    *score = Score::with_lives(config.starting_lives);
+    controller.0 = autopilot.controller();
+}
+
+fn setup_demo_system(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    attract: Res<Attract>,
+    mut recorder: ResMut<Recorder>,
+) {
+    if !attract.playing {
+        return;
+    }
+    recorder.discard();
+    let mut backdrop = theme.theme.backdrop;
+    backdrop.set_a(OVERLAY_ALPHA);
+    let menu = crate::main_menu::spawn_main_menu(&mut commands, &theme);
+    commands
+        .entity(menu)
+        .remove::<ThemedBackdrop>()
+        .insert(UiColor(backdrop));
+}
+
+/// Goes back to the menu on any input, and plays the part of the player otherwise.
+///
+/// Runs after everything else that sets the player's input, so the input that ends the demo
+/// does nothing in the game.
+#[allow(clippy::too_many_arguments)]
+pub fn demo_input_system(
+    state: Res<State<GameState>>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut cursor_events: EventReader<CursorMoved>,
+    mut attract: ResMut<Attract>,
+    mut transitions: ResMut<Transitions>,
+    mut playback: ResMut<Playback>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let input_seen = any_input(&mut keyboard_events, &mouse_buttons, &mut cursor_events);
+    let paused = match state.current() {
+        GameState::Level => false,
+        GameState::PauseMenu => true,
+        _ => return,
+    };
+    if !attract.playing {
+        return;
+    }
+    // a replay that no longer plays out as recorded would stand still after its last frame
+    let replay_over = playback.is_finished() && !transitions.is_busy();
+    if attract.leaving {
+        *input = PlayerInput::default();
+        return;
+    }
+    if input_seen || replay_over {
+        *input = PlayerInput::default();
+        attract.leaving = true;
+        playback.stop();
+        if paused {
+            transitions.pop(TransitionStyle::Cut);
+        }
+        transitions.set(GameState::MainMenu, TransitionStyle::Fade);
+        return;
+    }
+    // the AI does not see the pause menu, so carry on for it
+    if paused && !playback.is_active() && !transitions.is_busy() {
+        input.any_key = true;
+    }
+}
+
+/// Moves an AI demo on to another random level instead of the next one.
+fn next_level_system(
+    attract: Res<Attract>,
+    playback: Res<Playback>,
+    levels: Res<Vec<Level>>,
+    mut completed: EventReader<LevelCompleted>,
+    mut score: ResMut<Score>,
+) {
+    if completed.iter().count() == 0 || !attract.playing || playback.is_active() {
+        return;
+    }
+    if !levels.is_empty() {
+        score.current_level = rand::thread_rng().gen_range(0..levels.len());
+    }
+}
+
+fn skip_game_over_system(attract: Res<Attract>, mut transitions: ResMut<Transitions>) {
+    if attract.playing {
+        transitions.set(GameState::MainMenu, TransitionStyle::Fade);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idling_starts_the_demo_and_input_starts_the_wait_over() {
+        let mut attract = Attract::default();
+        assert!(!attract.tick(IDLE_SECONDS - 1.0, false));
+        assert!(!attract.tick(0.5, true));
+        assert!(!attract.tick(IDLE_SECONDS - 1.0, false));
+        assert!(attract.tick(1.0, false));
+    }
+}
@@ -0,0 +1,582 @@
+//! An AI that plays the paddle, either for the player or to help them out.
+//!
+//! The AI works out where the falling ball will come down, bouncing off the walls on the way,
+//! and steers the paddle there. Its difficulty sets how late it notices the ball changing course,
+//! how far off its guesses are, and which ball it goes for when there is more than one.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use crate::controls::{
+    ActiveController, BodyView, HumanController, PaddleController, PlayView, PlayerInput,
+};
+use crate::gameplay::paddle_rebound;
+use crate::locale::LocalizedText;
+use crate::playfield::Playfield;
+use crate::replay::Playback;
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedText};
+
+/// Switches between playing yourself, the assist and the autopilot.
+pub const TOGGLE_KEY: KeyCode = KeyCode::F6;
+
+/// Spots on the paddle the AI tries to catch a ball on when aiming for bricks, from -1 at its
+/// left edge to 1 at its right, the safer ones near the middle first.
+const AIM_OFFSETS: [f32; 9] = [0.0, -0.2, 0.2, -0.4, 0.4, -0.6, 0.6, -0.8, 0.8];
+
+/// Which ball the AI goes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AimStrategy {
+    /// Whichever ball comes down first.
+    Nearest,
+    /// A ball it can reach and send back into a brick, if there is one, caught on the part of
+    /// the paddle that turns it towards the brick.
+    Bricks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AiDifficulty {
+    /// Seconds it takes the AI to notice the ball has changed course.
+    pub reaction_delay: f32,
+    /// Furthest the AI misjudges where a ball comes down, in units.
+    pub error_margin: f32,
+    pub aim: AimStrategy,
+}
+
+impl AiDifficulty {
+    pub const EASY: AiDifficulty = AiDifficulty {
+        reaction_delay: 0.4,
+        error_margin: 70.0,
+        aim: AimStrategy::Nearest,
+    };
+    pub const NORMAL: AiDifficulty = AiDifficulty {
+        reaction_delay: 0.2,
+        error_margin: 30.0,
+        aim: AimStrategy::Nearest,
+    };
+    pub const HARD: AiDifficulty = AiDifficulty {
+        reaction_delay: 0.05,
+        error_margin: 5.0,
+        aim: AimStrategy::Bricks,
+    };
+}
+
+impl Default for AiDifficulty {
+    fn default() -> AiDifficulty {
+        AiDifficulty::NORMAL
+    }
+}
+
+impl FromStr for AiDifficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<AiDifficulty, String> {
+        match name {
+            "easy" => Ok(AiDifficulty::EASY),
+            "normal" => Ok(AiDifficulty::NORMAL),
+            "hard" => Ok(AiDifficulty::HARD),
+            _ => Err(format!(
+                "unknown difficulty {}, expected easy, normal or hard",
+                name
+            )),
+        }
+    }
+}
+
+/// Folds `x` back into `-half..=half` the way a ball bounces between walls at `-half` and
+/// `half`.
+fn fold_between_walls(x: f32, half: f32) -> f32 {
+    if half <= 0.0 {
+        return 0.0;
+    }
+    let period = 4.0 * half;
+    let offset = (x + half).rem_euclid(period);
+    if offset > 2.0 * half {
+        period - offset - half
+    } else {
+        offset - half
+    }
+}
+
+/// Where `ball` comes down to the height `catch_y`, and in how many seconds.
+///
+/// A rising ball is taken to bounce off the ceiling, since nothing tells where it will hit a
+/// brick; the AI finds out once it does.
+pub fn predict_landing(ball: &BodyView, playfield: &Playfield, catch_y: f32) -> Option<(f32, f32)> {
+    let velocity = ball.velocity;
+    if velocity.y == 0.0 {
+        return None;
+    }
+    let ceiling = playfield.half_height() - ball.size.y / 2.0;
+    let distance = if velocity.y < 0.0 {
+        ball.position.y - catch_y
+    } else {
+        (ceiling - ball.position.y) + (ceiling - catch_y)
+    };
+    if distance < 0.0 {
+        return None;
+    }
+    let time = distance / velocity.y.abs();
+    let half = playfield.half_width() - ball.size.x / 2.0;
+    let x = fold_between_walls(ball.position.x + velocity.x * time, half);
+    Some((x, time))
+}
+
+/// Whether `ball`, sent back up from `x` at the height `catch_y` with `velocity`, flies into a
+/// brick before reaching the ceiling.
+fn rebound_hits_brick(
+    ball: &BodyView,
+    velocity: Vec2,
+    x: f32,
+    catch_y: f32,
+    view: &PlayView,
+) -> bool {
+    if velocity.y <= 0.0 || view.bricks.is_empty() {
+        return false;
+    }
+    let ceiling = view.playfield.half_height() - ball.size.y / 2.0;
+    let half = view.playfield.half_width() - ball.size.x / 2.0;
+    // half a ball at a time, so no brick is stepped over
+    let step = (ball.size.y / 2.0).max(1.0) / velocity.y;
+    let mut time = 0.0;
+    loop {
+        let y = catch_y + velocity.y * time;
+        if y > ceiling {
+            return false;
+        }
+        let position = Vec2::new(fold_between_walls(x + velocity.x * time, half), y);
+        let hit = view.bricks.iter().any(|brick| {
+            let reach = (brick.size + ball.size) / 2.0;
+            let gap = (brick.position - position).abs();
+            gap.x < reach.x && gap.y < reach.y
+        });
+        if hit {
+            return true;
+        }
+        time += step;
+    }
+}
+
+/// Where on the paddle to catch `ball` at `x` so it bounces back into a brick, from -1 at the
+/// paddle's left edge to 1 at its right, or `None` if nowhere does.
+fn aim_offset(ball: &BodyView, x: f32, catch_y: f32, view: &PlayView) -> Option<f32> {
+    AIM_OFFSETS.iter().copied().find(|&offset| {
+        let velocity = paddle_rebound(ball.velocity, offset);
+        rebound_hits_brick(ball, velocity, x, catch_y, view)
+    })
+}
+
+/// Steers the paddle to where the ball will come down.
+pub struct AiController {
+    difficulty: AiDifficulty,
+    rng: StdRng,
+    /// Game time seen so far.
+    elapsed: f32,
+    /// Where the AI wanted the paddle on recent frames, oldest first, for the reaction delay.
+    plans: VecDeque<(f32, Option<f32>)>,
+    /// Where the AI last predicted a ball would come down, without the error.
+    last_prediction: Option<f32>,
+    /// How far the current guess is off.
+    error: f32,
+}
+
+impl AiController {
+    /// An AI of the given difficulty, whose mistakes are the same on every run with the same
+    /// `seed`.
+    pub fn new(difficulty: AiDifficulty, seed: Option<u64>) -> AiController {
+        AiController {
+            difficulty,
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            elapsed: 0.0,
+            plans: VecDeque::new(),
+            last_prediction: None,
+            error: 0.0,
+        }
+    }
+
+    /// Where the paddle should go to catch the ball the AI is after, as of now, with the ball
+    /// coming down on the spot of the paddle the AI aims with.
+    fn plan(&mut self, view: &PlayView) -> Option<f32> {
+        let paddle = view.paddle?;
+        let paddle_top = paddle.position.y + paddle.size.y / 2.0;
+        let mut landings: Vec<(&BodyView, f32, f32)> = view
+            .balls
+            .iter()
+            .filter_map(|ball| {
+                let catch_y = paddle_top + ball.size.y / 2.0;
+                let (x, time) = predict_landing(ball, &view.playfield, catch_y)?;
+                Some((ball, x, time))
+            })
+            .collect();
+        // falling balls first, then by how soon they come down
+        landings.sort_by(|a, b| {
+            let key = |(ball, _, time): &(&BodyView, f32, f32)| (ball.velocity.y >= 0.0, *time);
+            key(a)
+                .partial_cmp(&key(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let half_paddle = paddle.size.x / 2.0;
+        let chosen = match self.difficulty.aim {
+            AimStrategy::Nearest => None,
+            AimStrategy::Bricks => landings.iter().find_map(|(ball, x, time)| {
+                if ball.velocity.y >= 0.0 {
+                    return None;
+                }
+                let catch_y = paddle_top + ball.size.y / 2.0;
+                let offset = aim_offset(ball, *x, catch_y, view)?;
+                let reachable =
+                    (x - offset * half_paddle - paddle.position.x).abs() <= paddle.speed * time;
+                reachable.then_some((*x, offset))
+            }),
+        };
+        let (x, offset) = chosen.or_else(|| landings.first().map(|(_, x, _)| (*x, 0.0)))?;
+
+        // a new course gets a new misjudgement
+        let new_course = match self.last_prediction {
+            Some(last) => (x - last).abs() > paddle.size.x / 2.0,
+            None => true,
+        };
+        if new_course && self.difficulty.error_margin > 0.0 {
+            let margin = self.difficulty.error_margin;
+            self.error = self.rng.gen_range(-margin..=margin);
+        }
+        self.last_prediction = Some(x);
+        Some(x - offset * half_paddle + self.error)
+    }
+}
+
+impl PaddleController for AiController {
+    fn control(&mut self, view: &PlayView, input: &mut PlayerInput) {
+        self.elapsed += view.delta;
+        let plan = self.plan(view);
+        self.plans.push_back((self.elapsed, plan));
+        // act on the newest plan that is at least the reaction delay old
+        let seen = self.elapsed - self.difficulty.reaction_delay;
+        while self.plans.len() > 1 && self.plans[1].0 <= seen {
+            self.plans.pop_front();
+        }
+        let target = match self.plans.front() {
+            Some(&(time, plan)) if time <= seen => plan,
+            // nothing the AI has had time to take in yet
+            _ => None,
+        };
+        input.direction = 0.0;
+        input.target = match (target, view.paddle) {
+            (Some(target), Some(paddle)) => {
+                let limit = view.playfield.half_width() - paddle.size.x / 2.0;
+                Some(target.clamp(-limit, limit))
+            }
+            _ => None,
+        };
+    }
+}
+
+/// Lets the player steer, and has the AI take over whenever they let go.
+pub struct AssistController {
+    ai: AiController,
+    /// The mouse target on the previous frame, to tell whether the mouse moved.
+    last_target: Option<f32>,
+}
+
+impl AssistController {
+    pub fn new(ai: AiController) -> AssistController {
+        AssistController {
+            ai,
+            last_target: None,
+        }
+    }
+}
+
+impl PaddleController for AssistController {
+    fn control(&mut self, view: &PlayView, input: &mut PlayerInput) {
+        let idle = input.direction == 0.0 && input.target == self.last_target;
+        self.last_target = input.target;
+        // the AI keeps following the ball while the player steers, so it is ready to take over
+        let mut assisted = *input;
+        self.ai.control(view, &mut assisted);
+        if idle {
+            input.direction = assisted.direction;
+            input.target = assisted.target;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutopilotMode {
+    Off,
+    /// The AI steers while the player does not.
+    Assist,
+    /// The AI steers on its own.
+    On,
+}
+
+/// Whether and how the AI steers the paddle.
+pub struct Autopilot {
+    pub mode: AutopilotMode,
+    pub difficulty: AiDifficulty,
+    /// Seed for the AI's mistakes; different ones every time when `None`.
+    pub seed: Option<u64>,
+}
+
+impl Autopilot {
+    /// The autopilot at `difficulty`, or no AI at all when `None`.
+    pub fn new(difficulty: Option<AiDifficulty>, seed: Option<u64>) -> Autopilot {
+        Autopilot {
+            mode: match difficulty {
+                Some(_) => AutopilotMode::On,
+                None => AutopilotMode::Off,
+            },
+            difficulty: difficulty.unwrap_or_default(),
+            seed,
+        }
+    }
+
+    /// A fresh controller for the current mode.
+    pub fn controller(&self) -> Box<dyn PaddleController> {
+        let ai = || AiController::new(self.difficulty, self.seed);
+        match self.mode {
+            AutopilotMode::Off => Box::new(HumanController),
+            AutopilotMode::Assist => Box::new(AssistController::new(ai())),
+            AutopilotMode::On => Box::new(ai()),
+        }
+    }
+}
+
+/// Cycles through playing yourself, the assist and the autopilot.
+pub fn toggle_autopilot_system(keys: Res<Input<KeyCode>>, mut autopilot: ResMut<Autopilot>) {
+    if !keys.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    autopilot.mode = match autopilot.mode {
+        AutopilotMode::Off => AutopilotMode::Assist,
+        AutopilotMode::Assist => AutopilotMode::On,
+        AutopilotMode::On => AutopilotMode::Off,
+    };
+    info!("Autopilot: {:?}", autopilot.mode);
+}
+
+/// Hands the paddle to the controller for the autopilot's mode whenever it changes.
+pub fn apply_autopilot_system(autopilot: Res<Autopilot>, mut controller: ResMut<ActiveController>) {
+    if autopilot.is_changed() {
+        controller.0 = autopilot.controller();
+    }
+}
+
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Level).with_system(setup_autopilot_hud)
+}
+
+/// Shows whether the AI is steering.
+#[derive(Component)]
+pub struct AutopilotStatus;
+
+fn setup_autopilot_hud(mut commands: Commands, theme: Res<ActiveTheme>) {
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "",
+                theme.text_style(ThemedText::Small),
+                TextAlignment::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(LocalizedText::new("autopilot.on"))
+        .insert(ThemedText::Small)
+        .insert(AutopilotStatus);
+}
+
+pub fn update_autopilot_hud_system(
+    autopilot: Res<Autopilot>,
+    playback: Res<Playback>,
+    mut status_query: Query<(&mut LocalizedText, &mut Visibility), With<AutopilotStatus>>,
+) {
+    // a replay is steered by its recording, whatever the autopilot says
+    let key = match autopilot.mode {
+        _ if playback.is_active() => None,
+        AutopilotMode::Off => None,
+        AutopilotMode::Assist => Some("autopilot.assist"),
+        AutopilotMode::On => Some("autopilot.on"),
+    };
+    for (mut label, mut visibility) in status_query.iter_mut() {
+        if visibility.is_visible != key.is_some() {
+            visibility.is_visible = key.is_some();
+        }
+        if let Some(key) = key {
+            if label.key != key {
+                *label = LocalizedText::new(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::PaddleView;
+
+    const PLAYFIELD: Playfield = Playfield {
+        width: 1000.0,
+        height: 800.0,
+    };
+
+    fn ball(position: Vec2, velocity: Vec2) -> BodyView {
+        BodyView {
+            position,
+            size: Vec2::new(20.0, 20.0),
+            velocity,
+        }
+    }
+
+    fn view(balls: Vec<BodyView>) -> PlayView {
+        PlayView {
+            delta: 0.1,
+            playfield: PLAYFIELD,
+            paddle: Some(PaddleView {
+                position: Vec2::new(0.0, -300.0),
+                size: Vec2::new(160.0, 30.0),
+                speed: 500.0,
+            }),
+            balls,
+            bricks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn falling_ball_lands_after_bouncing_off_a_wall() {
+        // 490 is as far right as the ball goes; it has 100 units to go down and 200 across
+        let falling = ball(Vec2::new(390.0, 0.0), Vec2::new(200.0, -100.0));
+        let (x, time) = predict_landing(&falling, &PLAYFIELD, -100.0).unwrap();
+        assert!((time - 1.0).abs() < 1e-4);
+        assert!((x - 390.0).abs() < 1e-3, "landed at {}", x);
+    }
+
+    #[test]
+    fn rising_ball_comes_back_from_the_ceiling() {
+        // the ceiling is at 390 for this ball, 190 up and then 490 down
+        let rising = ball(Vec2::new(0.0, 200.0), Vec2::new(-100.0, 100.0));
+        let (x, time) = predict_landing(&rising, &PLAYFIELD, -100.0).unwrap();
+        assert!((time - 6.8).abs() < 1e-4);
+        // 680 units to the left, the last 190 of them back from the left wall at -490
+        assert!((x - -300.0).abs() < 1e-2, "landed at {}", x);
+    }
+
+    #[test]
+    fn ai_reacts_after_its_reaction_delay() {
+        let difficulty = AiDifficulty {
+            reaction_delay: 0.15,
+            error_margin: 0.0,
+            aim: AimStrategy::Nearest,
+        };
+        let mut ai = AiController::new(difficulty, Some(0));
+        let mut input = PlayerInput::default();
+        let targets: Vec<Option<f32>> = (0..4)
+            .map(|_| {
+                // the ball comes down where it is, at x = 100
+                ai.control(
+                    &view(vec![ball(Vec2::new(100.0, 0.0), Vec2::new(0.0, -100.0))]),
+                    &mut input,
+                );
+                input.target
+            })
+            .collect();
+        assert_eq!(targets, vec![None, None, Some(100.0), Some(100.0)]);
+    }
+
+    #[test]
+    fn ai_goes_for_the_ball_that_comes_down_first() {
+        let mut ai = AiController::new(AiDifficulty::HARD, Some(0));
+        let near = ball(Vec2::new(-200.0, -100.0), Vec2::new(0.0, -300.0));
+        let far = ball(Vec2::new(200.0, 300.0), Vec2::new(0.0, -300.0));
+        let mut view = view(vec![far, near]);
+        view.delta = 1.0;
+        let mut input = PlayerInput::default();
+        ai.control(&view, &mut input);
+        ai.control(&view, &mut input);
+        let target = input.target.unwrap();
+        assert!((target - -200.0).abs() <= AiDifficulty::HARD.error_margin);
+    }
+
+    #[test]
+    fn brick_aiming_returns_the_ball_that_hits_a_brick() {
+        let difficulty = AiDifficulty {
+            reaction_delay: 0.0,
+            error_margin: 0.0,
+            aim: AimStrategy::Bricks,
+        };
+        let mut ai = AiController::new(difficulty, Some(0));
+        // both balls fall straight down, but only one has a brick above it
+        let first = ball(Vec2::new(-200.0, -100.0), Vec2::new(0.0, -300.0));
+        let second = ball(Vec2::new(100.0, 0.0), Vec2::new(0.0, -300.0));
+        let mut view = view(vec![first, second]);
+        view.bricks.push(BodyView {
+            position: Vec2::new(100.0, 300.0),
+            size: Vec2::new(80.0, 40.0),
+            velocity: Vec2::ZERO,
+        });
+        let mut input = PlayerInput::default();
+        ai.control(&view, &mut input);
+        assert_eq!(input.target, Some(100.0));
+    }
+
+    #[test]
+    fn brick_aiming_catches_the_ball_on_the_side_that_turns_it_to_the_brick() {
+        let difficulty = AiDifficulty {
+            reaction_delay: 0.0,
+            error_margin: 0.0,
+            aim: AimStrategy::Bricks,
+        };
+        let target = |brick_x: f32| {
+            let mut ai = AiController::new(difficulty, Some(0));
+            let mut view = view(vec![ball(Vec2::ZERO, Vec2::new(0.0, -300.0))]);
+            view.bricks.push(BodyView {
+                position: Vec2::new(brick_x, 300.0),
+                size: Vec2::new(80.0, 40.0),
+                velocity: Vec2::ZERO,
+            });
+            let mut input = PlayerInput::default();
+            ai.control(&view, &mut input);
+            input.target.unwrap()
+        };
+        // the ball comes straight down at x = 0 either way
+        assert_eq!(target(0.0), 0.0);
+        assert!(target(120.0) < 0.0, "caught on the right of the paddle");
+        assert!(target(-120.0) > 0.0, "caught on the left of the paddle");
+        assert_eq!(target(120.0), -target(-120.0));
+    }
+
+    #[test]
+    fn assist_leaves_the_paddle_to_a_steering_player() {
+        let difficulty = AiDifficulty {
+            error_margin: 0.0,
+            ..AiDifficulty::HARD
+        };
+        let mut assist = AssistController::new(AiController::new(difficulty, Some(0)));
+        let view = view(vec![ball(Vec2::new(100.0, 0.0), Vec2::new(0.0, -100.0))]);
+        let mut steering = PlayerInput {
+            direction: -1.0,
+            ..Default::default()
+        };
+        assist.control(&view, &mut steering);
+        assert_eq!(steering.direction, -1.0);
+        assert_eq!(steering.target, None);
+        // the AI kept up with the ball all along, so it takes over at once
+        let mut idle = PlayerInput::default();
+        assist.control(&view, &mut idle);
+        assert_eq!(idle.target, Some(100.0));
+    }
+}
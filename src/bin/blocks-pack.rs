@@ -0,0 +1,331 @@
+//! Packs a folder of PNGs into a single sprite sheet image and writes the matching
+//! `.sheet.ron` file.
+//!
+//! ```text
+//! blocks-pack <input dir> <output png> [--padding N] [--extrude N] [--width N]
+//!             [--ron PATH] [--texture PATH]
+//! ```
+//!
+//! Each sprite is named after its file, without the extension. Only names and rectangles are
+//! written; pivots, hitboxes and animations have to be added to the RON afterwards. The same
+//! inputs always produce byte-identical output.
+
+use image::RgbaImage;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: blocks-pack <input dir> <output png> [--padding N] [--extrude N] \
+                     [--width N] [--ron PATH] [--texture PATH]";
+
+struct Options {
+    input: PathBuf,
+    output: PathBuf,
+    /// Transparent pixels left between neighbouring sprites.
+    padding: u32,
+    /// Pixels of each sprite's border repeated around it, so filtering never samples a
+    /// neighbour.
+    extrude: u32,
+    /// Width of the atlas; its height grows to fit.
+    width: u32,
+    /// Where to write the sprite sheet RON; defaults to the output PNG with `.sheet.ron`.
+    ron: Option<PathBuf>,
+    /// Texture path written into the RON, relative to the assets folder; defaults to the
+    /// output PNG with any leading `assets/` removed.
+    texture: Option<String>,
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let mut positional = Vec::new();
+    let mut options = Options {
+        input: PathBuf::new(),
+        output: PathBuf::new(),
+        padding: 2,
+        extrude: 1,
+        width: 512,
+        ron: None,
+        texture: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--padding" => options.padding = value()?.parse()?,
+            "--extrude" => options.extrude = value()?.parse()?,
+            "--width" => options.width = value()?.parse()?,
+            "--ron" => options.ron = Some(PathBuf::from(value()?)),
+            "--texture" => options.texture = Some(value()?),
+            "-h" | "--help" => return Err(USAGE.into()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg).into()),
+            _ => positional.push(arg),
+        }
+    }
+    match positional.as_slice() {
+        [input, output] => {
+            options.input = PathBuf::from(input);
+            options.output = PathBuf::from(output);
+            Ok(options)
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+struct Sprite {
+    name: String,
+    image: RgbaImage,
+    x: u32,
+    y: u32,
+}
+
+fn load_sprites(dir: &Path) -> Result<Vec<Sprite>, Box<dyn Error>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or(format!("{} is not a valid sprite name", path.display()))?
+                .to_string();
+            let image = image::open(&path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?
+                .to_rgba8();
+            Ok(Sprite {
+                name,
+                image,
+                x: 0,
+                y: 0,
+            })
+        })
+        .collect()
+}
+
+/// Places sprites on shelves, tallest first, and returns the height of the atlas.
+fn pack(sprites: &mut [Sprite], options: &Options) -> Result<u32, Box<dyn Error>> {
+    // sort by size, then name, so equal inputs always pack the same way
+    sprites.sort_by(|a, b| {
+        (b.image.height(), b.image.width(), &a.name).cmp(&(
+            a.image.height(),
+            a.image.width(),
+            &b.name,
+        ))
+    });
+    let border = options.extrude + options.padding;
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for sprite in sprites.iter_mut() {
+        let width = sprite.image.width() + 2 * border;
+        let height = sprite.image.height() + 2 * border;
+        if width > options.width {
+            return Err(format!(
+                "{} is {} pixels wide with padding, wider than the atlas",
+                sprite.name, width
+            )
+            .into());
+        }
+        if x + width > options.width {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        sprite.x = x + border;
+        sprite.y = y + border;
+        x += width;
+        shelf_height = shelf_height.max(height);
+    }
+    Ok(y + shelf_height)
+}
+
+/// Copies each sprite into the atlas, repeating its edge pixels `extrude` times around it.
+fn draw(sprites: &[Sprite], width: u32, height: u32, extrude: u32) -> RgbaImage {
+    let mut atlas = RgbaImage::new(width, height);
+    for sprite in sprites {
+        let (w, h) = sprite.image.dimensions();
+        let e = extrude as i64;
+        for dy in -e..h as i64 + e {
+            for dx in -e..w as i64 + e {
+                let source_x = dx.clamp(0, w as i64 - 1) as u32;
+                let source_y = dy.clamp(0, h as i64 - 1) as u32;
+                let pixel = *sprite.image.get_pixel(source_x, source_y);
+                atlas.put_pixel(
+                    (sprite.x as i64 + dx) as u32,
+                    (sprite.y as i64 + dy) as u32,
+                    pixel,
+                );
+            }
+        }
+    }
+    atlas
+}
+
+fn sheet_ron(sprites: &[Sprite], texture: &str, width: u32, height: u32) -> String {
+    let mut sorted: Vec<&Sprite> = sprites.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut ron = format!(
+        "(\n    texture: {:?},\n    texture_width: {},\n    texture_height: {},\n    sprites: [\n",
+        texture, width, height
+    );
+    for sprite in sorted {
+        ron.push_str(&format!(
+            "        SpriteSpecs(name: {:?}, x: {}, y: {}, width: {}, height: {}),\n",
+            sprite.name,
+            sprite.x,
+            sprite.y,
+            sprite.image.width(),
+            sprite.image.height()
+        ));
+    }
+    ron.push_str("    ],\n)\n");
+    ron
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let options = parse_args()?;
+    let mut sprites = load_sprites(&options.input)?;
+    if sprites.is_empty() {
+        return Err(format!("no PNG files in {}", options.input.display()).into());
+    }
+    let height = pack(&mut sprites, &options)?;
+    let atlas = draw(&sprites, options.width, height, options.extrude);
+    atlas.save(&options.output)?;
+
+    let ron_path = options
+        .ron
+        .clone()
+        .unwrap_or_else(|| options.output.with_extension("sheet.ron"));
+    let texture = options.texture.clone().unwrap_or_else(|| {
+        let output = options.output.to_string_lossy().replace('\\', "/");
+        output
+            .strip_prefix("assets/")
+            .unwrap_or(&output)
+            .to_string()
+    });
+    std::fs::write(
+        &ron_path,
+        sheet_ron(&sprites, &texture, options.width, height),
+    )?;
+    println!(
+        "Packed {} sprites into {} ({}x{}) and {}",
+        sprites.len(),
+        options.output.display(),
+        atlas.width(),
+        height,
+        ron_path.display()
+    );
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("blocks-pack: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn options(width: u32) -> Options {
+        Options {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            padding: 2,
+            extrude: 1,
+            width,
+            ron: None,
+            texture: None,
+        }
+    }
+
+    fn sprite(name: &str, width: u32, height: u32) -> Sprite {
+        Sprite {
+            name: name.to_string(),
+            image: RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255])),
+            x: 0,
+            y: 0,
+        }
+    }
+
+    fn placements(sprites: &[Sprite]) -> Vec<(String, u32, u32)> {
+        let mut placed: Vec<_> = sprites
+            .iter()
+            .map(|sprite| (sprite.name.clone(), sprite.x, sprite.y))
+            .collect();
+        placed.sort();
+        placed
+    }
+
+    #[test]
+    fn sprites_are_packed_on_shelves_tallest_first() {
+        let mut sprites = vec![
+            sprite("small", 10, 10),
+            sprite("tall", 20, 30),
+            sprite("wide", 40, 20),
+        ];
+        let height = pack(&mut sprites, &options(64)).unwrap();
+        // each sprite takes three pixels of extrusion and padding on every side, so the wide one
+        // starts a new shelf and the small one still fits next to it
+        assert_eq!(
+            placements(&sprites),
+            vec![
+                ("small".to_string(), 49, 39),
+                ("tall".to_string(), 3, 3),
+                ("wide".to_string(), 3, 39),
+            ]
+        );
+        assert_eq!(height, 62);
+    }
+
+    #[test]
+    fn packing_does_not_depend_on_file_order() {
+        let mut forwards = vec![sprite("a", 10, 10), sprite("b", 10, 10), sprite("c", 12, 8)];
+        let mut backwards = vec![sprite("c", 12, 8), sprite("b", 10, 10), sprite("a", 10, 10)];
+        pack(&mut forwards, &options(40)).unwrap();
+        pack(&mut backwards, &options(40)).unwrap();
+        assert_eq!(placements(&forwards), placements(&backwards));
+    }
+
+    #[test]
+    fn sprites_wider_than_the_atlas_are_an_error() {
+        let mut sprites = vec![sprite("huge", 60, 10)];
+        assert!(pack(&mut sprites, &options(64)).is_err());
+    }
+
+    #[test]
+    fn edges_are_extruded() {
+        let mut sprites = vec![sprite("red", 2, 2)];
+        let height = pack(&mut sprites, &options(8)).unwrap();
+        let atlas = draw(&sprites, 8, height, 1);
+        let red = Rgba([255, 0, 0, 255]);
+        let clear = Rgba([0, 0, 0, 0]);
+        // the sprite sits at (3, 3), with one extruded pixel around it and padding beyond
+        assert_eq!(*atlas.get_pixel(3, 3), red);
+        assert_eq!(*atlas.get_pixel(2, 2), red);
+        assert_eq!(*atlas.get_pixel(5, 5), red);
+        assert_eq!(*atlas.get_pixel(1, 1), clear);
+        assert_eq!(*atlas.get_pixel(6, 6), clear);
+    }
+
+    #[test]
+    fn the_written_sheet_loads_in_the_game() {
+        let mut sprites = vec![sprite("paddle", 30, 10), sprite("ball", 8, 8)];
+        let height = pack(&mut sprites, &options(64)).unwrap();
+        let ron = sheet_ron(&sprites, "texture/packed.png", 64, height);
+        let (sheet, atlas, texture) =
+            bevy_blocks::sprite_sheet::SpriteSheet::from_ron(ron.as_bytes()).unwrap();
+        assert_eq!(texture, "texture/packed.png");
+        assert_eq!(atlas.size.x, 64.0);
+        assert_eq!(atlas.size.y, height as f32);
+        let paddle = sheet.index("paddle").unwrap();
+        assert_eq!(sheet.size(paddle).x, 30.0);
+        assert_eq!(atlas.textures[paddle].min.x, 3.0);
+    }
+}
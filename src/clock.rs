@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Frame times of the windowed game are rounded to multiples of this, a quarter of a 60 Hz
+/// frame, so frames at a steady frame rate all come out the same length and replays of them
+/// compress well.
+pub const QUANTUM: Duration = Duration::from_nanos(1_000_000_000 / 240);
+
+/// Length of the current frame as seen by gameplay.
+///
+/// Follows `Time`, unless a fixed step is set: then every frame lasts exactly that long however
+/// long it really took, so a headless run plays out the same way every time. A quantized clock
+/// follows `Time` in whole quanta, carrying what is left over into the next frame. Anything that
+/// decides what happens in a level should read this instead of `Time`; purely cosmetic effects
+/// can keep using `Time`.
+#[derive(Debug, Default)]
+pub struct GameTime {
+    delta: Duration,
+    fixed_step: Option<Duration>,
+    quantum: Option<Duration>,
+    /// Time not yet handed out by a quantized clock.
+    carry: Duration,
+}
+
+impl GameTime {
+    /// A clock that advances by `step` every frame.
+    pub fn fixed(step: Duration) -> GameTime {
+        GameTime {
+            delta: step,
+            fixed_step: Some(step),
+            ..Default::default()
+        }
+    }
+
+    /// A clock that follows `Time` in multiples of `quantum`.
+    pub fn quantized(quantum: Duration) -> GameTime {
+        GameTime {
+            quantum: Some(quantum),
+            ..Default::default()
+        }
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Starts a new frame after `real` time has passed.
+    fn advance(&mut self, real: Duration) {
+        self.delta = match (self.fixed_step, self.quantum) {
+            (Some(step), _) => step,
+            (None, Some(quantum)) => {
+                let elapsed = self.carry + real;
+                let delta = quantum * (elapsed.as_nanos() / quantum.as_nanos()) as u32;
+                self.carry = elapsed - delta;
+                delta
+            }
+            (None, None) => real,
+        };
+    }
+
+    /// Overrides the length of the current frame, e.g. to hold gameplay still.
+    pub fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+}
+
+/// Starts the gameplay frame with the frame time from `Time`.
+pub fn update_game_time_system(time: Res<Time>, mut game_time: ResMut<GameTime>) {
+    game_time.advance(time.delta());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantized_clock_carries_the_remainder() {
+        let mut time = GameTime::quantized(Duration::from_millis(4));
+        let deltas: Vec<u128> = (0..3)
+            .map(|_| {
+                time.advance(Duration::from_millis(10));
+                time.delta().as_millis()
+            })
+            .collect();
+        // never more than has really passed, and nothing lost
+        assert_eq!(deltas, vec![8, 12, 8]);
+    }
+}
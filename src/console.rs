@@ -0,0 +1,694 @@
+//! Drop-down developer console, only built with the `console` feature.
+//!
+//! The backquote key opens it. Commands are looked up in [`ConsoleCommands`], which starts out
+//! with the cheats testers need; other modules can register their own with [`add_command`].
+
+use bevy::ecs::event::Events;
+use bevy::ecs::system::CommandQueue;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::events::LevelCompleted;
+use crate::gameplay::{self, Ball, Collider, GodMode, Paddle, Score, BALL_SPEED};
+use crate::level::Level;
+use crate::powerup::{ActivePowerUps, PowerUpKind, POWERUP_SECONDS};
+use crate::sprite_sheet::{GameSpriteSheet, SpriteSheet};
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedText};
+use crate::transition::{TransitionStyle, Transitions};
+use crate::BlocksConfig;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+/// Lines of output kept around.
+const MAX_OUTPUT: usize = 100;
+/// Lines of output shown above the prompt.
+const VISIBLE_OUTPUT: usize = 12;
+
+pub type CommandFn = Arc<dyn Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync>;
+
+/// A console command: how to call it and what it does.
+pub struct ConsoleCommand {
+    /// Shown by `help`, e.g. `"lives <n>"`.
+    pub usage: String,
+    pub help: String,
+    /// Values tab completion offers for the first argument.
+    pub completions: Vec<String>,
+    /// Runs the command with its arguments, returning the reply or what went wrong.
+    pub run: CommandFn,
+}
+
+impl ConsoleCommand {
+    pub fn new(
+        usage: &str,
+        help: &str,
+        run: impl Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync + 'static,
+    ) -> ConsoleCommand {
+        ConsoleCommand {
+            usage: usage.to_string(),
+            help: help.to_string(),
+            completions: Vec::new(),
+            run: Arc::new(run),
+        }
+    }
+
+    pub fn with_completions(mut self, completions: Vec<String>) -> ConsoleCommand {
+        self.completions = completions;
+        self
+    }
+}
+
+/// Every command the console knows, by name.
+#[derive(Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+impl ConsoleCommands {
+    /// Adds a command, replacing any command of the same name.
+    pub fn add(&mut self, name: &str, command: ConsoleCommand) {
+        self.commands.insert(name.to_string(), command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.commands.get(name)
+    }
+
+    /// Command names in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+}
+
+/// Registers a console command; modules call this from their setup.
+pub fn add_command(app: &mut App, name: &str, command: ConsoleCommand) {
+    app.init_resource::<ConsoleCommands>();
+    app.world
+        .resource_mut::<ConsoleCommands>()
+        .add(name, command);
+}
+
+/// Runs a command line against the world, returning the reply or what went wrong.
+pub fn run_command(world: &mut World, line: &str) -> Result<String, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, args) = match words.split_first() {
+        Some(split) => split,
+        None => return Ok(String::new()),
+    };
+    let run = match world.resource::<ConsoleCommands>().get(name) {
+        Some(command) => command.run.clone(),
+        None => return Err(format!("Unknown command {}, try help", name)),
+    };
+    let reply = run(world, args);
+    // anything but looking things up changes the game behind the recording's back
+    if reply.is_ok() && !matches!(*name, "help" | "clear") {
+        if let Some(mut recorder) = world.get_resource_mut::<crate::replay::Recorder>() {
+            recorder.discard();
+        }
+    }
+    reply
+}
+
+/// The console's text: what is being typed, what was typed before and the replies.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    /// Commands and their replies, oldest first.
+    pub output: Vec<String>,
+    history: Vec<String>,
+    /// Position in `history` while browsing it; `None` while typing a new line.
+    browsing: Option<usize>,
+    /// Lines submitted but not run yet.
+    pending: Vec<String>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        if self.output.len() > MAX_OUTPUT {
+            let excess = self.output.len() - MAX_OUTPUT;
+            self.output.drain(..excess);
+        }
+    }
+
+    /// Sends the typed line off to be run and remembers it.
+    pub fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.browsing = None;
+        self.print(format!("> {}", line));
+        if line.trim().is_empty() {
+            return;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.pending.push(line);
+    }
+
+    /// Recalls the line before the one shown.
+    pub fn previous(&mut self) {
+        let index = match self.browsing {
+            Some(0) => 0,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        self.browsing = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    /// Recalls the line after the one shown, or an empty line past the newest.
+    pub fn next(&mut self) {
+        match self.browsing {
+            Some(index) if index + 1 < self.history.len() => {
+                self.browsing = Some(index + 1);
+                self.input = self.history[index + 1].clone();
+            }
+            Some(_) => {
+                self.browsing = None;
+                self.input.clear();
+            }
+            None => (),
+        }
+    }
+
+    /// Completes the command name, or its first argument, as far as it is unambiguous, and
+    /// lists the choices when there are several.
+    pub fn complete(&mut self, commands: &ConsoleCommands) {
+        let (prefix, word, candidates): (String, String, Vec<&str>) =
+            match self.input.split_once(' ') {
+                None => (
+                    String::new(),
+                    self.input.clone(),
+                    commands.names().collect(),
+                ),
+                Some((name, argument)) if !argument.contains(' ') => match commands.get(name) {
+                    Some(command) => (
+                        format!("{} ", name),
+                        argument.to_string(),
+                        command.completions.iter().map(String::as_str).collect(),
+                    ),
+                    None => return,
+                },
+                Some(_) => return,
+            };
+        let matches: Vec<&str> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(&word))
+            .collect();
+        let completed = match matches.as_slice() {
+            [] => return,
+            [only] => format!("{}{} ", prefix, only),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.len(), |len, other| {
+                    first
+                        .chars()
+                        .zip(other.chars())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                let listing = matches.join("  ");
+                let completed = format!("{}{}", prefix, &first[..common]);
+                self.print(listing);
+                completed
+            }
+        };
+        self.input = completed;
+    }
+}
+
+#[derive(Component)]
+struct ConsolePanel;
+
+#[derive(Component)]
+struct ConsoleText;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                console_input_system.after(bevy::input::InputSystem),
+            )
+            .add_system(run_console_commands_system.exclusive_system())
+            .add_system(console_ui_system);
+        add_builtin_commands(app);
+    }
+}
+
+/// Edits the command line while the console is open, keeping the keys from the game.
+fn console_input_system(
+    mut console: ResMut<Console>,
+    commands: Res<ConsoleCommands>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+    mut keys: ResMut<Input<KeyCode>>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+    } else if console.open {
+        for character in characters.iter() {
+            if !character.char.is_control() && character.char != '`' {
+                console.input.push(character.char);
+            }
+        }
+        if keys.just_pressed(KeyCode::Back) {
+            console.input.pop();
+        }
+        if keys.just_pressed(KeyCode::Return) {
+            console.submit();
+        }
+        if keys.just_pressed(KeyCode::Up) {
+            console.previous();
+        }
+        if keys.just_pressed(KeyCode::Down) {
+            console.next();
+        }
+        if keys.just_pressed(KeyCode::Tab) {
+            console.complete(&commands);
+        }
+        if keys.just_pressed(KeyCode::Escape) {
+            console.open = false;
+        }
+    } else {
+        return;
+    }
+    characters.iter().for_each(drop);
+    keyboard_events.clear();
+    let pressed: Vec<KeyCode> = keys.get_pressed().copied().collect();
+    for key in pressed {
+        keys.reset(key);
+    }
+    keys.clear();
+}
+
+fn run_console_commands_system(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in pending {
+        let reply = match run_command(world, &line) {
+            Ok(reply) => reply,
+            Err(error) => error,
+        };
+        if !reply.is_empty() {
+            world.resource_mut::<Console>().print(reply);
+        }
+    }
+}
+
+/// Shows the panel while the console is open, spawning it again when a menu teardown removed
+/// it.
+fn console_ui_system(
+    mut commands: Commands,
+    console: Res<Console>,
+    theme: Res<ActiveTheme>,
+    panels: Query<Entity, With<ConsolePanel>>,
+    mut texts: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !console.open {
+        for panel in panels.iter() {
+            commands.entity(panel).despawn_recursive();
+        }
+        return;
+    }
+    let mut text = match texts.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => {
+            let mut backdrop = theme.theme.backdrop;
+            backdrop.set_a(0.9);
+            commands
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            left: Val::Px(0.0),
+                            top: Val::Px(0.0),
+                            ..Default::default()
+                        },
+                        size: Size::new(Val::Percent(100.0), Val::Percent(40.0)),
+                        align_items: AlignItems::FlexEnd,
+                        padding: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    color: UiColor(backdrop),
+                    ..Default::default()
+                })
+                .insert(ConsolePanel)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "",
+                                theme.text_style(ThemedText::Small),
+                                TextAlignment::default(),
+                            ),
+                            ..Default::default()
+                        })
+                        .insert(ConsoleText)
+                        .insert(ThemedText::Small);
+                });
+            return;
+        }
+    };
+    if console.is_changed() {
+        let start = console.output.len().saturating_sub(VISIBLE_OUTPUT);
+        let mut lines: Vec<&str> = console.output[start..].iter().map(String::as_str).collect();
+        let prompt = format!("> {}_", console.input);
+        lines.push(&prompt);
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn add_builtin_commands(app: &mut App) {
+    add_command(
+        app,
+        "help",
+        ConsoleCommand::new("help", "Lists every command", help),
+    );
+    add_command(
+        app,
+        "level",
+        ConsoleCommand::new("level <n>", "Starts level n, counting from 1", go_to_level),
+    );
+    add_command(
+        app,
+        "lives",
+        ConsoleCommand::new("lives <n>", "Sets the lives left", set_lives),
+    );
+    add_command(
+        app,
+        "score",
+        ConsoleCommand::new("score <n>", "Sets the points", set_score),
+    );
+    add_command(
+        app,
+        "clear",
+        ConsoleCommand::new("clear", "Removes every brick, completing the level", clear),
+    );
+    add_command(
+        app,
+        "spawn_ball",
+        ConsoleCommand::new(
+            "spawn_ball",
+            "Serves another ball from the paddle",
+            spawn_ball,
+        ),
+    );
+    add_command(
+        app,
+        "powerup",
+        ConsoleCommand::new("powerup <name>", "Starts a power-up", start_powerup).with_completions(
+            PowerUpKind::ALL
+                .iter()
+                .map(|kind| powerup_name(*kind).to_string())
+                .collect(),
+        ),
+    );
+    add_command(
+        app,
+        "speed",
+        ConsoleCommand::new(
+            "speed <f>",
+            "Sets the ball speed relative to its serve",
+            set_speed,
+        ),
+    );
+    add_command(
+        app,
+        "godmode",
+        ConsoleCommand::new(
+            "godmode",
+            "Toggles bouncing off the bottom",
+            toggle_god_mode,
+        ),
+    );
+}
+
+/// The single argument of a command, parsed.
+fn argument<T: FromStr>(args: &[&str]) -> Result<T, String> {
+    match args {
+        [arg] => arg
+            .parse()
+            .map_err(|_| format!("Cannot understand {}", arg)),
+        _ => Err("Expected one argument".to_string()),
+    }
+}
+
+fn in_level(world: &World) -> Result<(), String> {
+    match world.resource::<State<GameState>>().current() {
+        GameState::Level | GameState::PauseMenu => Ok(()),
+        _ => Err("Only available during a level".to_string()),
+    }
+}
+
+fn powerup_name(kind: PowerUpKind) -> &'static str {
+    kind.name_key().trim_start_matches("powerup.")
+}
+
+fn help(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let commands = world.resource::<ConsoleCommands>();
+    Ok(commands
+        .commands
+        .values()
+        .map(|command| format!("{} - {}", command.usage, command.help))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn go_to_level(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let number: usize = argument(args)?;
+    let count = world.resource::<Vec<Level>>().len();
+    if number == 0 || number > count {
+        return Err(format!("Levels go from 1 to {}", count));
+    }
+    let current = world.resource::<State<GameState>>().current().clone();
+    if let GameState::Loading | GameState::LoadingFailed = current {
+        return Err("Still loading".to_string());
+    }
+    if let GameState::Level | GameState::PauseMenu = current {
+        world.resource_mut::<Score>().current_level = number - 1;
+    } else {
+        // coming from a menu, so start a fresh game
+        let lives = world.resource::<BlocksConfig>().starting_lives;
+        let mut score = Score::with_lives(lives);
+        score.current_level = number - 1;
+        world.insert_resource(score);
+    }
+    let mut transitions = world.resource_mut::<Transitions>();
+    match current {
+        GameState::Level => transitions.restart(TransitionStyle::Fade),
+        GameState::PauseMenu => {
+            transitions.pop(TransitionStyle::Cut);
+            transitions.restart(TransitionStyle::Fade);
+        }
+        _ => transitions.set(GameState::Level, TransitionStyle::Fade),
+    }
+    Ok(format!("Going to level {}", number))
+}
+
+fn set_lives(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let lives: i32 = argument(args)?;
+    if lives <= 0 {
+        return Err("Lives must be at least 1".to_string());
+    }
+    world.resource_mut::<Score>().lives = lives;
+    Ok(format!("Lives set to {}", lives))
+}
+
+fn set_score(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let points: i32 = argument(args)?;
+    world.resource_mut::<Score>().points = points;
+    Ok(format!("Points set to {}", points))
+}
+
+fn clear(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    if world.resource::<State<GameState>>().current() != &GameState::Level {
+        return Err("Only available while playing a level".to_string());
+    }
+    let bricks: Vec<Entity> = world
+        .query::<(Entity, &Collider)>()
+        .iter(world)
+        .filter(|(_, collider)| matches!(collider, Collider::Scorable { .. }))
+        .map(|(entity, _)| entity)
+        .collect();
+    for brick in bricks {
+        world.despawn(brick);
+    }
+    let mut score = world.resource_mut::<Score>();
+    score.bricks_left = 0;
+    let level = score.current_level;
+    world
+        .resource_mut::<Events<LevelCompleted>>()
+        .send(LevelCompleted { level });
+    Ok("Level cleared".to_string())
+}
+
+fn spawn_ball(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    in_level(world)?;
+    let position = world
+        .query_filtered::<&Transform, With<Paddle>>()
+        .iter(world)
+        .next()
+        .map(|transform| transform.translation + Vec3::new(0.0, 40.0, 1.0))
+        .ok_or_else(|| "There is no paddle to serve from".to_string())?;
+    let mut queue = CommandQueue::default();
+    {
+        let handle = &world.resource::<GameSpriteSheet>().0;
+        let sheet = world
+            .resource::<Assets<SpriteSheet>>()
+            .get(handle)
+            .ok_or_else(|| "The sprite sheet is not loaded".to_string())?;
+        let mut commands = Commands::new(&mut queue, world);
+        gameplay::spawn_ball(
+            &mut commands,
+            sheet,
+            position,
+            BALL_SPEED * Vec3::new(-0.5, 0.5, 0.0).normalize(),
+        );
+    }
+    queue.apply(world);
+    Ok("Ball served".to_string())
+}
+
+fn start_powerup(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let name: String = argument(args)?;
+    let kind = PowerUpKind::ALL
+        .iter()
+        .copied()
+        .find(|kind| powerup_name(*kind) == name)
+        .ok_or_else(|| format!("No power-up called {}", name))?;
+    world
+        .resource_mut::<ActivePowerUps>()
+        .activate(kind, POWERUP_SECONDS);
+    Ok(format!("Started {}", name))
+}
+
+fn set_speed(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let factor: f32 = argument(args)?;
+    if factor <= 0.0 {
+        return Err("Speed must be above 0".to_string());
+    }
+    in_level(world)?;
+    for mut ball in world.query::<&mut Ball>().iter_mut(world) {
+        ball.velocity = ball.velocity.normalize_or_zero() * BALL_SPEED * factor;
+    }
+    Ok(format!("Ball speed set to {}x", factor))
+}
+
+fn toggle_god_mode(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut god_mode = world.resource_mut::<GodMode>();
+    god_mode.0 = !god_mode.0;
+    Ok(format!(
+        "God mode {}",
+        if god_mode.0 { "on" } else { "off" }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> ConsoleCommands {
+        let mut commands = ConsoleCommands::default();
+        let noop = |_: &mut World, _: &[&str]| Ok(String::new());
+        commands.add("score", ConsoleCommand::new("score <n>", "", noop));
+        commands.add("speed", ConsoleCommand::new("speed <f>", "", noop));
+        commands.add(
+            "powerup",
+            ConsoleCommand::new("powerup <name>", "", noop)
+                .with_completions(vec!["slow_ball".to_string(), "wide_paddle".to_string()]),
+        );
+        commands
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(commands());
+        world.insert_resource(Score::new());
+        world.init_resource::<GodMode>();
+        world.init_resource::<ActivePowerUps>();
+        world
+            .resource_mut::<ConsoleCommands>()
+            .add("lives", ConsoleCommand::new("lives <n>", "", set_lives));
+        world.resource_mut::<ConsoleCommands>().add(
+            "godmode",
+            ConsoleCommand::new("godmode", "", toggle_god_mode),
+        );
+        world.resource_mut::<ConsoleCommands>().add(
+            "powerup",
+            ConsoleCommand::new("powerup <name>", "", start_powerup),
+        );
+        world
+    }
+
+    #[test]
+    fn history_recalls_submitted_lines() {
+        let mut console = Console::default();
+        for line in ["lives 5", "score 10"] {
+            console.input = line.to_string();
+            console.submit();
+        }
+        console.previous();
+        assert_eq!(console.input, "score 10");
+        console.previous();
+        console.previous();
+        assert_eq!(console.input, "lives 5");
+        console.next();
+        assert_eq!(console.input, "score 10");
+        console.next();
+        assert_eq!(console.input, "");
+    }
+
+    #[test]
+    fn tab_completes_commands_and_arguments() {
+        let commands = commands();
+        let mut console = Console {
+            input: "po".to_string(),
+            ..Default::default()
+        };
+        console.complete(&commands);
+        assert_eq!(console.input, "powerup ");
+        console.input.push('w');
+        console.complete(&commands);
+        assert_eq!(console.input, "powerup wide_paddle ");
+    }
+
+    #[test]
+    fn ambiguous_completion_lists_the_choices() {
+        let commands = commands();
+        let mut console = Console {
+            input: "s".to_string(),
+            ..Default::default()
+        };
+        console.complete(&commands);
+        assert_eq!(console.input, "s");
+        assert_eq!(console.output.last().unwrap(), "score  speed");
+    }
+
+    #[test]
+    fn commands_change_the_game() {
+        let mut world = world();
+        assert!(run_command(&mut world, "lives 7").is_ok());
+        assert_eq!(world.resource::<Score>().lives, 7);
+        assert!(run_command(&mut world, "godmode").is_ok());
+        assert!(world.resource::<GodMode>().0);
+        assert!(run_command(&mut world, "powerup slow_ball").is_ok());
+        assert!(world
+            .resource::<ActivePowerUps>()
+            .is_active(PowerUpKind::SlowBall));
+    }
+
+    #[test]
+    fn bad_commands_are_reported() {
+        let mut world = world();
+        assert!(run_command(&mut world, "teleport").is_err());
+        assert!(run_command(&mut world, "lives lots").is_err());
+        assert!(run_command(&mut world, "powerup laser").is_err());
+        assert_eq!(world.resource::<Score>().lives, 3);
+    }
+}
@@ -0,0 +1,181 @@
+//! What the player asks of the game each frame, whichever device it came from, and the
+//! controllers that may steer the paddle in the player's place.
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::gameplay::{Ball, Collider, Paddle};
+use crate::playfield::Playfield;
+use crate::powerup::ActivePowerUps;
+use crate::settings::{ControlScheme, Settings};
+use crate::state::GameState;
+
+/// The player's input for the current frame.
+///
+/// Gameplay and the pause menu read this instead of the keyboard and mouse, so a replay can
+/// put recorded input in its place.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PlayerInput {
+    /// Paddle movement from the keys, from -1 for full speed left to 1 for full speed right.
+    pub direction: f32,
+    /// Where the mouse wants the paddle, along the playfield's x axis. Takes over from
+    /// `direction` when set.
+    pub target: Option<f32>,
+    /// The pause key went down this frame.
+    pub pause: bool,
+    /// A key went down this frame.
+    pub any_key: bool,
+}
+
+/// Label of [`read_player_input_system`]. Systems that swallow raw input run before it, and
+/// systems that replace the player's input run after it.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSystem;
+
+/// Reads [`PlayerInput`] from the keyboard and mouse, following the control scheme setting.
+pub fn read_player_input_system(
+    keys: Res<Input<KeyCode>>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    settings: Res<Settings>,
+    playfield: Res<Playfield>,
+    windows: Res<Windows>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let (left_key, right_key) = match settings.control_scheme {
+        ControlScheme::Wasd => (KeyCode::A, KeyCode::D),
+        _ => (KeyCode::Left, KeyCode::Right),
+    };
+    let mut direction = 0.0;
+    if keys.pressed(left_key) {
+        direction -= 1.0;
+    }
+    if keys.pressed(right_key) {
+        direction += 1.0;
+    }
+    let target = match settings.control_scheme {
+        ControlScheme::Mouse => windows.get_primary().and_then(|window| {
+            let position = window.cursor_position()?;
+            let cursor = playfield.window_to_playfield(position, window.width(), window.height());
+            Some(cursor.x)
+        }),
+        _ => None,
+    };
+    *input = PlayerInput {
+        direction,
+        target,
+        // only the press counts, so the key that resumes the game does not pause it again
+        pause: keys.just_pressed(KeyCode::Space) || keys.just_pressed(KeyCode::P),
+        any_key: keyboard_events
+            .iter()
+            .any(|evt| evt.state == ElementState::Pressed),
+    };
+}
+
+/// Label of [`control_paddle_system`].
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PaddleControlSystem;
+
+/// Something that steers the paddle, by adjusting the input read from the player.
+pub trait PaddleController: Send + Sync {
+    /// Adjusts this frame's `input` after looking at the level.
+    fn control(&mut self, view: &PlayView, input: &mut PlayerInput);
+}
+
+/// Leaves the paddle to the player's own input.
+pub struct HumanController;
+
+impl PaddleController for HumanController {
+    fn control(&mut self, _view: &PlayView, _input: &mut PlayerInput) {}
+}
+
+/// The controller steering the paddle.
+pub struct ActiveController(pub Box<dyn PaddleController>);
+
+impl Default for ActiveController {
+    fn default() -> ActiveController {
+        ActiveController(Box::new(HumanController))
+    }
+}
+
+/// What a controller gets to see of the level, in playfield space and units per second.
+#[derive(Debug, Clone, Default)]
+pub struct PlayView {
+    /// Length of the current frame in seconds.
+    pub delta: f32,
+    pub playfield: Playfield,
+    pub paddle: Option<PaddleView>,
+    pub balls: Vec<BodyView>,
+    pub bricks: Vec<BodyView>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaddleView {
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Top speed along the x axis.
+    pub speed: f32,
+}
+
+/// A ball or brick. Bricks never move.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyView {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub velocity: Vec2,
+}
+
+/// Lets the active controller adjust the player's input while a level is being played.
+#[allow(clippy::too_many_arguments)]
+pub fn control_paddle_system(
+    state: Res<State<GameState>>,
+    time: Res<crate::clock::GameTime>,
+    playfield: Res<Playfield>,
+    powerups: Res<ActivePowerUps>,
+    mut controller: ResMut<ActiveController>,
+    mut input: ResMut<PlayerInput>,
+    paddle_query: Query<(&Paddle, &Collider, &Transform)>,
+    ball_query: Query<(&Ball, &Transform)>,
+    collider_query: Query<(&Collider, &Transform)>,
+) {
+    if *state.current() != GameState::Level {
+        return;
+    }
+    let paddle = paddle_query
+        .get_single()
+        .ok()
+        .map(|(paddle, collider, transform)| PaddleView {
+            position: transform.translation.truncate(),
+            size: collider.get_size(),
+            speed: paddle.speed(),
+        });
+    let balls = ball_query
+        .iter()
+        .map(|(ball, transform)| BodyView {
+            position: transform.translation.truncate(),
+            size: ball.size,
+            velocity: ball.velocity.truncate() * powerups.ball_speed_factor(),
+        })
+        .collect();
+    let bricks = collider_query
+        .iter()
+        .filter_map(|(collider, transform)| match collider {
+            Collider::Scorable { size } => Some(BodyView {
+                position: transform.translation.truncate(),
+                size: *size,
+                velocity: Vec2::ZERO,
+            }),
+            _ => None,
+        })
+        .collect();
+    let view = PlayView {
+        delta: time.delta_seconds(),
+        playfield: *playfield,
+        paddle,
+        balls,
+        bricks,
+    };
+    controller.0.control(&view, &mut input);
+}
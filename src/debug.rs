@@ -0,0 +1,368 @@
+//! Developer overlay, only built with the `debug-tools` feature.
+//!
+//! F3 shows collider outlines, the ball's velocity, the last contact normal and a panel with the
+//! state stack, score and entity counts. F4 holds gameplay still and F5 then advances it by one
+//! tick at a time.
+
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::clock::GameTime;
+use crate::events::BallBounced;
+use crate::gameplay::{Ball, Collider, Score};
+use crate::particles::Particle;
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedText};
+
+pub const TOGGLE_OVERLAY_KEY: KeyCode = KeyCode::F3;
+pub const TOGGLE_FRAME_STEP_KEY: KeyCode = KeyCode::F4;
+pub const STEP_KEY: KeyCode = KeyCode::F5;
+
+/// Length of one frame-step tick.
+const STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// The velocity arrow shows where the ball will be this many seconds from now.
+const VELOCITY_ARROW_SECONDS: f32 = 0.25;
+const NORMAL_ARROW_LENGTH: f32 = 40.0;
+const LINE_WIDTH: f32 = 2.0;
+
+const BRICK_COLOR: Color = Color::rgb(0.2, 1.0, 0.2);
+const PADDLE_COLOR: Color = Color::rgb(0.2, 0.8, 1.0);
+const BALL_COLOR: Color = Color::rgb(1.0, 1.0, 0.2);
+const VELOCITY_COLOR: Color = Color::rgb(1.0, 0.3, 1.0);
+const NORMAL_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+    /// Gameplay only advances when `STEP_KEY` is pressed.
+    pub frame_step: bool,
+}
+
+/// Where the ball last bounced and which way the surface faced.
+#[derive(Default)]
+pub struct LastContact(pub Option<(Vec3, Vec2)>);
+
+/// Marks an entity that already has its outline.
+#[derive(Component)]
+struct Outlined;
+
+/// One side of an outline, as a child of the outlined entity.
+#[derive(Component)]
+struct OutlineEdge(usize);
+
+#[derive(Component)]
+struct VelocityArrow;
+
+#[derive(Component)]
+struct NormalArrow;
+
+#[derive(Component)]
+struct DebugText;
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<LastContact>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                frame_step_system.after(bevy::input::InputSystem),
+            )
+            .add_system(toggle_overlay_system)
+            .add_system(record_contacts_system)
+            .add_system(add_outlines_system)
+            .add_system(update_outlines_system)
+            .add_system(update_velocity_arrows_system)
+            .add_system(update_normal_arrow_system)
+            .add_system(debug_text_system);
+    }
+}
+
+fn toggle_overlay_system(keys: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keys.just_pressed(TOGGLE_OVERLAY_KEY) {
+        overlay.visible = !overlay.visible;
+    }
+    if keys.just_pressed(TOGGLE_FRAME_STEP_KEY) {
+        overlay.frame_step = !overlay.frame_step;
+    }
+}
+
+/// Stops the gameplay clock while frame-stepping, letting one tick through per key press.
+fn frame_step_system(
+    keys: Res<Input<KeyCode>>,
+    overlay: Res<DebugOverlay>,
+    mut time: ResMut<GameTime>,
+) {
+    if overlay.frame_step {
+        let step = keys.just_pressed(STEP_KEY);
+        time.set_delta(if step { STEP } else { Duration::ZERO });
+    }
+}
+
+fn record_contacts_system(
+    mut bounces: EventReader<BallBounced>,
+    mut last_contact: ResMut<LastContact>,
+) {
+    if let Some(bounce) = bounces.iter().last() {
+        last_contact.0 = Some((bounce.position, bounce.normal));
+    }
+}
+
+/// Colliders and balls that have no outline yet.
+type WithoutOutline = (Or<(With<Collider>, With<Ball>)>, Without<Outlined>);
+
+/// Gives every collider and ball an outline, and every ball a velocity arrow.
+fn add_outlines_system(
+    mut commands: Commands,
+    query: Query<(Entity, Option<&Collider>), WithoutOutline>,
+) {
+    for (entity, collider) in query.iter() {
+        let color = match collider {
+            Some(Collider::Scorable { .. }) => BRICK_COLOR,
+            Some(Collider::Paddle { .. }) => PADDLE_COLOR,
+            None => BALL_COLOR,
+        };
+        commands
+            .entity(entity)
+            .insert(Outlined)
+            .with_children(|parent| {
+                for side in 0..4 {
+                    parent
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color,
+                                ..Default::default()
+                            },
+                            visibility: Visibility { is_visible: false },
+                            ..Default::default()
+                        })
+                        .insert(OutlineEdge(side));
+                }
+                if collider.is_none() {
+                    parent
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color: VELOCITY_COLOR,
+                                ..Default::default()
+                            },
+                            visibility: Visibility { is_visible: false },
+                            ..Default::default()
+                        })
+                        .insert(VelocityArrow);
+                }
+            });
+    }
+}
+
+/// Fits the outlines to their collider, which can change size, and removes those whose entity
+/// is gone.
+fn update_outlines_system(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    owners: Query<(Option<&Collider>, Option<&Ball>)>,
+    mut edges: Query<(
+        Entity,
+        &Parent,
+        &OutlineEdge,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    for (entity, parent, edge, mut sprite, mut transform, mut visibility) in edges.iter_mut() {
+        let size = match owners.get(parent.0) {
+            Ok((Some(collider), _)) => collider.get_size(),
+            Ok((None, Some(ball))) => ball.size,
+            _ => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        visibility.is_visible = overlay.visible;
+        let (offset, edge_size) = match edge.0 {
+            0 => (Vec2::new(0.0, size.y / 2.0), Vec2::new(size.x, LINE_WIDTH)),
+            1 => (Vec2::new(0.0, -size.y / 2.0), Vec2::new(size.x, LINE_WIDTH)),
+            2 => (Vec2::new(-size.x / 2.0, 0.0), Vec2::new(LINE_WIDTH, size.y)),
+            _ => (Vec2::new(size.x / 2.0, 0.0), Vec2::new(LINE_WIDTH, size.y)),
+        };
+        sprite.custom_size = Some(edge_size);
+        transform.translation = offset.extend(0.5);
+    }
+}
+
+fn update_velocity_arrows_system(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    balls: Query<&Ball>,
+    mut arrows: Query<
+        (
+            Entity,
+            &Parent,
+            &mut Sprite,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<VelocityArrow>,
+    >,
+) {
+    for (entity, parent, mut sprite, mut transform, mut visibility) in arrows.iter_mut() {
+        let ball = match balls.get(parent.0) {
+            Ok(ball) => ball,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        visibility.is_visible = overlay.visible;
+        let velocity = ball.velocity.truncate();
+        let length = velocity.length() * VELOCITY_ARROW_SECONDS;
+        sprite.custom_size = Some(Vec2::new(length, LINE_WIDTH));
+        *transform = arrow_transform(velocity.normalize_or_zero(), length);
+    }
+}
+
+fn update_normal_arrow_system(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    last_contact: Res<LastContact>,
+    balls: Query<(), With<Ball>>,
+    mut arrows: Query<(&mut Transform, &mut Visibility), With<NormalArrow>>,
+) {
+    let (position, normal) = match last_contact.0 {
+        Some(contact) => contact,
+        None => return,
+    };
+    let mut transform = arrow_transform(normal, NORMAL_ARROW_LENGTH);
+    transform.translation += position.truncate().extend(2.0);
+    match arrows.get_single_mut() {
+        Ok((mut arrow_transform, mut visibility)) => {
+            *arrow_transform = transform;
+            visibility.is_visible = overlay.visible && !balls.is_empty();
+        }
+        Err(_) => {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: NORMAL_COLOR,
+                        custom_size: Some(Vec2::new(NORMAL_ARROW_LENGTH, LINE_WIDTH)),
+                        ..Default::default()
+                    },
+                    transform,
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                })
+                .insert(NormalArrow);
+        }
+    }
+}
+
+/// A line of `length` starting at the origin and pointing along `direction`.
+fn arrow_transform(direction: Vec2, length: f32) -> Transform {
+    Transform {
+        translation: (direction * length / 2.0).extend(0.6),
+        rotation: Quat::from_rotation_z(direction.y.atan2(direction.x)),
+        ..Default::default()
+    }
+}
+
+/// Shows the inspector panel, spawning it again when a menu teardown removed it.
+#[allow(clippy::too_many_arguments)]
+fn debug_text_system(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    theme: Res<ActiveTheme>,
+    state: Res<State<GameState>>,
+    score: Res<Score>,
+    last_contact: Res<LastContact>,
+    diagnostics: Res<Diagnostics>,
+    entities: Query<Entity>,
+    colliders: Query<&Collider>,
+    balls: Query<&Ball>,
+    particles: Query<(), With<Particle>>,
+    mut texts: Query<(Entity, &mut Text), With<DebugText>>,
+) {
+    if !overlay.visible {
+        for (entity, _) in texts.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+    let mut text = match texts.get_single_mut() {
+        Ok((_, text)) => text,
+        Err(_) => {
+            commands
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        theme.text_style(ThemedText::Small),
+                        TextAlignment::default(),
+                    ),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            left: Val::Px(10.0),
+                            top: Val::Px(10.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(DebugText)
+                .insert(ThemedText::Small);
+            return;
+        }
+    };
+
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.average())
+        .unwrap_or_default();
+    let stack: Vec<String> = state
+        .inactives()
+        .iter()
+        .chain(std::iter::once(state.current()))
+        .map(|state| format!("{:?}", state))
+        .collect();
+    let bricks = colliders
+        .iter()
+        .filter(|collider| matches!(collider, Collider::Scorable { .. }))
+        .count();
+    let mut lines = vec![
+        format!("FPS: {:.1}", fps),
+        format!("States: {}", stack.join(" > ")),
+        format!(
+            "Level: {}  Points: {}  Lives: {}  Bricks left: {}  Combo: {}",
+            score.current_level, score.points, score.lives, score.bricks_left, score.combo
+        ),
+        format!(
+            "Entities: {}  Colliders: {}  Bricks: {}  Balls: {}  Particles: {}",
+            entities.iter().count(),
+            colliders.iter().count(),
+            bricks,
+            balls.iter().count(),
+            particles.iter().count()
+        ),
+    ];
+    for ball in balls.iter() {
+        let velocity = ball.velocity.truncate();
+        lines.push(format!(
+            "Ball velocity: ({:.0}, {:.0}) speed {:.0}",
+            velocity.x,
+            velocity.y,
+            velocity.length()
+        ));
+    }
+    if let Some((position, normal)) = last_contact.0 {
+        lines.push(format!(
+            "Last contact: ({:.0}, {:.0}) normal ({:.2}, {:.2})",
+            position.x, position.y, normal.x, normal.y
+        ));
+    }
+    if overlay.frame_step {
+        lines.push(format!("Frame step: press {:?} to advance", STEP_KEY));
+    }
+    text.sections[0].value = lines.join("\n");
+}
@@ -0,0 +1,146 @@
+//! The screen shown when the game cannot carry on with what it was doing, such as a level whose
+//! paddle has gone missing. Instead of aborting, the game leaves for this screen and from there
+//! goes back to the main menu.
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+
+use crate::locale::LocalizedText;
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedBackdrop, ThemedText};
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
+
+/// What went wrong, while the error screen is on its way or showing.
+#[derive(Default)]
+pub struct GameError {
+    pub message: Option<String>,
+}
+
+impl GameError {
+    /// Logs `message` and leaves whatever is on screen for the error screen.
+    ///
+    /// Only the first report is kept until the error screen is dismissed, so systems that keep
+    /// failing every frame while the transition plays do not flood the log.
+    pub fn report(&mut self, transitions: &mut Transitions, message: impl Into<String>) {
+        if self.message.is_some() {
+            return;
+        }
+        let message = message.into();
+        error!("{}", message);
+        self.message = Some(message);
+        transitions.replace(GameState::Error, TransitionStyle::Fade);
+    }
+}
+
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Error).with_system(setup_error_screen)
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Error).with_system(dismiss_system)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Error).with_system(teardown)
+}
+
+fn setup_error_screen(mut commands: Commands, theme: Res<ActiveTheme>, error: Res<GameError>) {
+    let message = error.message.clone().unwrap_or_default();
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: UiColor(theme.theme.backdrop),
+            ..Default::default()
+        })
+        .insert(ThemedBackdrop)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        theme.text_style(ThemedText::Title),
+                        TextAlignment::default(),
+                    ),
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(LocalizedText::new("error.title"))
+                .insert(ThemedText::Title);
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        message,
+                        theme.text_style(ThemedText::Small),
+                        TextAlignment::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(ThemedText::Small);
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        theme.text_style(ThemedText::Body),
+                        TextAlignment::default(),
+                    ),
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(LocalizedText::new("error.continue"))
+                .insert(ThemedText::Body);
+        });
+}
+
+/// Goes back to the main menu on any key press or click.
+fn dismiss_system(
+    mut keyboard_input: EventReader<KeyboardInput>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut transitions: ResMut<Transitions>,
+) {
+    let key_pressed = keyboard_input
+        .iter()
+        .any(|evt| evt.state == ElementState::Pressed);
+    if key_pressed || mouse_buttons.get_just_pressed().next().is_some() {
+        transitions.set(GameState::MainMenu, TransitionStyle::Fade);
+    }
+}
+
+fn teardown(
+    mut commands: Commands,
+    mut error: ResMut<GameError>,
+    menu_nodes: Query<(Entity, &Node), Without<TransitionOverlay>>,
+) {
+    error.message = None;
+    for (e, _n) in menu_nodes.iter() {
+        commands.entity(e).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_first_report_is_kept() {
+        let mut error = GameError::default();
+        let mut transitions = Transitions::default();
+        error.report(&mut transitions, "No paddle");
+        error.report(&mut transitions, "No ball");
+        assert_eq!(error.message.as_deref(), Some("No paddle"));
+        assert!(transitions.is_busy());
+    }
+}
@@ -0,0 +1,75 @@
+//! Events describing what happens during play.
+//!
+//! The core gameplay systems only detect what happened and send one of these; scoring, lives,
+//! level progression, power-up drops, sound and effects are each handled by their own system
+//! reading the events they care about.
+
+use bevy::prelude::*;
+
+/// The ball touched a brick.
+pub struct BrickHit {
+    pub brick: Entity,
+    pub position: Vec3,
+    pub size: Vec2,
+    /// Ball speed relative to its serving speed.
+    pub strength: f32,
+}
+
+/// A brick broke and was removed from the level.
+pub struct BrickDestroyed {
+    pub position: Vec3,
+    pub size: Vec2,
+    /// The brick's atlas and sprite index, so debris can look like it.
+    pub sprite: Option<(Handle<TextureAtlas>, usize)>,
+}
+
+/// The ball bounced off the paddle.
+pub struct PaddleHit {
+    /// Where the ball was when it touched the paddle.
+    pub position: Vec3,
+    /// Ball speed relative to its serving speed.
+    pub strength: f32,
+}
+
+/// The ball bounced off a side or the top of the playfield.
+pub struct WallHit {
+    pub position: Vec3,
+    /// Ball speed relative to its serving speed.
+    pub strength: f32,
+}
+
+/// The ball bounced off anything: a wall, a brick or the paddle.
+pub struct BallBounced {
+    pub position: Vec3,
+    /// Direction the surface the ball bounced off faces.
+    pub normal: Vec2,
+}
+
+/// The ball fell through the bottom of the playfield.
+pub struct BallLost {
+    pub ball: Entity,
+    pub position: Vec3,
+}
+
+/// A life was taken away after the ball was lost.
+pub struct LifeLost {
+    pub lives_left: i32,
+}
+
+/// The last brick of a level was destroyed.
+pub struct LevelCompleted {
+    /// Index of the completed level.
+    pub level: usize,
+}
+
+/// Registers every gameplay event.
+pub fn add_events(app: &mut App) {
+    app.add_event::<BrickHit>()
+        .add_event::<BrickDestroyed>()
+        .add_event::<PaddleHit>()
+        .add_event::<WallHit>()
+        .add_event::<BallBounced>()
+        .add_event::<BallLost>()
+        .add_event::<LifeLost>()
+        .add_event::<LevelCompleted>();
+}
@@ -0,0 +1,314 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashMap;
+
+use crate::events::{BallLost, BrickDestroyed, BrickHit, PaddleHit, WallHit};
+use crate::gameplay::{Ball, Paddle};
+use crate::playfield::MainCamera;
+use crate::powerup::Pickup;
+use crate::settings::Settings;
+use crate::state::GameState;
+use crate::GameApp;
+
+/// Largest distance the camera moves from its resting place, at full trauma and intensity.
+const MAX_SHAKE_OFFSET: f32 = 12.0;
+/// Trauma lost per second.
+const SHAKE_DECAY: f32 = 1.5;
+/// Length of the freeze after a brick breaks, at full intensity.
+const HIT_STOP_SECONDS: f32 = 0.06;
+const SQUASH_SECONDS: f32 = 0.15;
+/// How much of its height the paddle loses at the peak of a squash, at full intensity.
+const SQUASH_AMOUNT: f32 = 0.35;
+const FLASH_SECONDS: f32 = 0.12;
+/// Trauma added to the screen shake by each impact, with the ball at serving speed.
+const BRICK_TRAUMA: f32 = 0.3;
+const PADDLE_TRAUMA: f32 = 0.15;
+const WALL_TRAUMA: f32 = 0.05;
+const BALL_LOST_TRAUMA: f32 = 0.8;
+
+/// Screen shake, hit-stop, paddle squash and flashing bricks, none of which change how a game
+/// plays out.
+pub struct FeelPlugin;
+
+impl Plugin for FeelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>()
+            .init_resource::<HitStop>()
+            .init_resource::<PaddleSquash>()
+            .add_system(camera_shake_system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                hold_frame_system.after(bevy::transform::TransformSystem::TransformPropagate),
+            )
+            .add_game_system_set(update_system_set())
+            .add_game_system_set(exit_system_set());
+    }
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Level)
+        .with_system(brick_impacts_system)
+        .with_system(bounce_impacts_system)
+        .with_system(tick_hit_stop_system)
+        .with_system(paddle_squash_system)
+        .with_system(flash_system)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Level).with_system(teardown)
+}
+
+/// Screen shake, stored as a trauma level between 0 and 1 that decays over time.
+///
+/// Offsets grow with the square of trauma, so small knocks barely move the camera while big
+/// ones stack up quickly.
+#[derive(Default)]
+pub struct ScreenShake {
+    trauma: f32,
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+}
+
+/// A short freeze of the picture after a brick breaks.
+///
+/// Only what is drawn stands still: the ball, paddle and pickups are shown where they were when
+/// the brick broke, and particles, flashes and sprite animations wait, while the game itself
+/// carries on underneath and catches up on screen once the freeze is over.
+#[derive(Default)]
+pub struct HitStop {
+    timer: Option<Timer>,
+    /// Where each held entity is drawn until the freeze is over.
+    held: HashMap<Entity, GlobalTransform>,
+}
+
+impl HitStop {
+    pub fn is_frozen(&self) -> bool {
+        self.timer.is_some()
+    }
+}
+
+#[derive(Default)]
+pub struct PaddleSquash {
+    timer: Option<Timer>,
+}
+
+/// A white panel over a brick that was just hit, fading out.
+#[derive(Component)]
+pub struct Flash {
+    timer: Timer,
+    alpha: f32,
+}
+
+/// Shakes the screen, flashes bricks that were hit and freezes briefly when one breaks.
+fn brick_impacts_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut brick_hits: EventReader<BrickHit>,
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut shake: ResMut<ScreenShake>,
+    mut hit_stop: ResMut<HitStop>,
+) {
+    for hit in brick_hits.iter() {
+        shake.add_trauma(BRICK_TRAUMA * hit.strength);
+        if settings.flash > 0.0 {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1.0, 1.0, 1.0, settings.flash),
+                        custom_size: Some(hit.size),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(hit.position.x, hit.position.y, 1.5),
+                    ..Default::default()
+                })
+                .insert(Flash {
+                    timer: Timer::from_seconds(FLASH_SECONDS, false),
+                    alpha: settings.flash,
+                });
+        }
+    }
+    if destroyed.iter().count() > 0 && settings.hit_stop > 0.0 {
+        hit_stop.timer = Some(Timer::from_seconds(
+            HIT_STOP_SECONDS * settings.hit_stop,
+            false,
+        ));
+    }
+}
+
+/// Shakes the screen when the ball bounces or is lost, and squashes the paddle it hits.
+fn bounce_impacts_system(
+    mut paddle_hits: EventReader<PaddleHit>,
+    mut wall_hits: EventReader<WallHit>,
+    mut balls_lost: EventReader<BallLost>,
+    mut shake: ResMut<ScreenShake>,
+    mut squash: ResMut<PaddleSquash>,
+) {
+    for hit in paddle_hits.iter() {
+        shake.add_trauma(PADDLE_TRAUMA * hit.strength);
+        squash.timer = Some(Timer::from_seconds(SQUASH_SECONDS, false));
+    }
+    for hit in wall_hits.iter() {
+        shake.add_trauma(WALL_TRAUMA * hit.strength);
+    }
+    for _ in balls_lost.iter() {
+        shake.add_trauma(BALL_LOST_TRAUMA);
+    }
+}
+
+fn tick_hit_stop_system(time: Res<Time>, mut hit_stop: ResMut<HitStop>) {
+    let finished = match hit_stop.timer.as_mut() {
+        Some(timer) => timer.tick(time.delta()).finished(),
+        None => return,
+    };
+    if finished {
+        hit_stop.timer = None;
+    }
+}
+
+/// What hit-stop holds still on screen.
+type Held = Or<(With<Ball>, With<Paddle>, With<Pickup>)>;
+
+/// Draws the ball, paddle and pickups where they were when the freeze started, and back where
+/// they really are once it is over.
+///
+/// Runs after the transforms have been propagated, so only the picture is held.
+pub fn hold_frame_system(
+    mut hit_stop: ResMut<HitStop>,
+    mut held_query: Query<(Entity, &Transform, &mut GlobalTransform), Held>,
+) {
+    let hit_stop = &mut *hit_stop;
+    if hit_stop.is_frozen() {
+        for (entity, _, mut global) in held_query.iter_mut() {
+            *global = *hit_stop.held.entry(entity).or_insert(*global);
+        }
+    } else if !hit_stop.held.is_empty() {
+        // transforms that did not change since are not propagated again
+        for (entity, transform, mut global) in held_query.iter_mut() {
+            if hit_stop.held.contains_key(&entity) {
+                *global = GlobalTransform::from(*transform);
+            }
+        }
+        hit_stop.held.clear();
+    }
+}
+
+/// Moves the main camera around its resting place according to the current trauma.
+///
+/// Runs in every state so the camera always settles back once the shake has worn off.
+pub fn camera_shake_system(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut shake: ResMut<ScreenShake>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    if shake.trauma <= 0.0 {
+        return;
+    }
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.0);
+    let amount = MAX_SHAKE_OFFSET * shake.trauma * shake.trauma * settings.screen_shake;
+    // presentation only, so this does not draw from `GameRng`
+    let mut rng = rand::thread_rng();
+    let offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * amount;
+    for mut transform in camera_query.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
+
+fn paddle_squash_system(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    hit_stop: Res<HitStop>,
+    mut squash: ResMut<PaddleSquash>,
+    mut paddle_query: Query<&mut Transform, With<Paddle>>,
+) {
+    if hit_stop.is_frozen() {
+        return;
+    }
+    let progress = match squash.timer.as_mut() {
+        Some(timer) => timer.tick(time.delta()).percent_left(),
+        None => return,
+    };
+    // only the height changes; the width belongs to the wide-paddle power-up
+    let scale = 1.0 - SQUASH_AMOUNT * settings.squash * progress;
+    for mut transform in paddle_query.iter_mut() {
+        transform.scale.y = scale;
+    }
+    if progress <= 0.0 {
+        squash.timer = None;
+    }
+}
+
+fn flash_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    hit_stop: Res<HitStop>,
+    mut flash_query: Query<(Entity, &mut Flash, &mut Sprite)>,
+) {
+    if hit_stop.is_frozen() {
+        return;
+    }
+    for (entity, mut flash, mut sprite) in flash_query.iter_mut() {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(flash.alpha * flash.timer.percent_left());
+        }
+    }
+}
+
+fn teardown(
+    mut commands: Commands,
+    mut hit_stop: ResMut<HitStop>,
+    mut squash: ResMut<PaddleSquash>,
+    flash_query: Query<Entity, With<Flash>>,
+) {
+    hit_stop.timer = None;
+    hit_stop.held.clear();
+    squash.timer = None;
+    for entity in flash_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_picture_holds_while_the_game_moves_on() {
+        let mut app = App::new();
+        app.init_resource::<HitStop>().add_system(hold_frame_system);
+        let ball = app
+            .world
+            .spawn()
+            .insert(Ball {
+                velocity: Vec3::ZERO,
+                size: Vec2::ONE,
+            })
+            .insert(Transform::from_xyz(1.0, 2.0, 0.0))
+            .insert(GlobalTransform::from_xyz(1.0, 2.0, 0.0))
+            .id();
+        app.world.resource_mut::<HitStop>().timer = Some(Timer::from_seconds(0.1, false));
+        app.update();
+
+        // the game moves the ball, and propagation would draw it there
+        app.world.get_mut::<Transform>(ball).unwrap().translation.x = 5.0;
+        *app.world.get_mut::<GlobalTransform>(ball).unwrap() =
+            GlobalTransform::from_xyz(5.0, 2.0, 0.0);
+        app.update();
+        let drawn = app.world.get::<GlobalTransform>(ball).unwrap().translation;
+        assert_eq!(drawn, Vec3::new(1.0, 2.0, 0.0));
+
+        app.world.resource_mut::<HitStop>().timer = None;
+        app.update();
+        let drawn = app.world.get::<GlobalTransform>(ball).unwrap().translation;
+        assert_eq!(drawn, Vec3::new(5.0, 2.0, 0.0));
+        assert!(app.world.resource::<HitStop>().held.is_empty());
+    }
+}
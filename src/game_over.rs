@@ -1,10 +1,9 @@
+use crate::locale::LocalizedText;
 use crate::state::GameState;
-use bevy::app::AppExit;
+use crate::theme::{ActiveTheme, ThemedBackdrop, ThemedText};
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
 use bevy::prelude::*;
 
-#[derive(Component)]
-struct PauseUI;
-
 pub fn enter_system_set() -> SystemSet {
     SystemSet::on_enter(crate::state::GameState::GameOver).with_system(setup_game_over)
 }
@@ -16,12 +15,8 @@ pub fn update_system_set() -> SystemSet {
 pub fn exit_system_set() -> SystemSet {
     SystemSet::on_exit(GameState::GameOver).with_system(teardown)
 }
-pub fn setup_game_over(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let text_style = TextStyle {
-        font: asset_server.load("font/FiraSans-Light.ttf"),
-        font_size: 40.0,
-        color: Color::rgb(0.71, 0.8, 0.4),
-    };
+pub fn setup_game_over(mut commands: Commands, theme: Res<ActiveTheme>) {
+    let text_style = theme.text_style(ThemedText::Body);
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -31,14 +26,10 @@ pub fn setup_game_over(mut commands: Commands, asset_server: Res<AssetServer>) {
                 justify_content: JustifyContent::SpaceAround,
                 ..Default::default()
             },
-            color: UiColor(Color::Rgba {
-                red: 0.1,
-                green: 0.1,
-                blue: 0.1,
-                alpha: 1.0,
-            }),
+            color: UiColor(theme.theme.backdrop),
             ..Default::default()
         })
+        .insert(ThemedBackdrop)
         .with_children(|grandparent| {
             grandparent
                 .spawn_bundle(NodeBundle {
@@ -57,7 +48,7 @@ pub fn setup_game_over(mut commands: Commands, asset_server: Res<AssetServer>) {
                         .with_children(|btn| {
                             btn.spawn_bundle(TextBundle {
                                 text: Text::with_section(
-                                    "Back",
+                                    "",
                                     text_style.clone(),
                                     TextAlignment {
                                         vertical: VerticalAlign::Center,
@@ -69,45 +60,43 @@ pub fn setup_game_over(mut commands: Commands, asset_server: Res<AssetServer>) {
                                     ..Default::default()
                                 },
                                 ..Default::default()
-                            });
+                            })
+                            .insert(LocalizedText::new("menu.back"))
+                            .insert(ThemedText::Body);
                         });
-                    parent.spawn_bundle(TextBundle {
-                        text: Text::with_section(
-                            "Congratulations!\nYou won the game.".to_string(),
-                            text_style.clone(),
-                            TextAlignment {
-                                vertical: VerticalAlign::Top,
-                                horizontal: HorizontalAlign::Center,
-                            },
-                        ),
-                        ..Default::default()
-                    });
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "",
+                                text_style.clone(),
+                                TextAlignment {
+                                    vertical: VerticalAlign::Top,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            ..Default::default()
+                        })
+                        .insert(LocalizedText::new("game_over.won"))
+                        .insert(ThemedText::Body);
                 });
         });
 }
 
 fn menu_update(
-    mut commands: Commands,
-    mut state: ResMut<State<crate::state::GameState>>,
-    mut interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
-    mut text_query: Query<&mut Text>,
-    mut exit: EventWriter<AppExit>,
+    mut transitions: ResMut<Transitions>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
 ) {
-    for (interaction, children) in interaction_query.iter_mut() {
-        let text = text_query.get_mut(children[0]).unwrap();
-        match *interaction {
-            Interaction::Clicked => {
-                state
-                    .set(GameState::MainMenu)
-                    .expect("Failed to return to main menu");
-            }
-
-            _ => (),
+    for interaction in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            transitions.set(GameState::MainMenu, TransitionStyle::Fade);
         }
     }
 }
 
-fn teardown(mut commands: Commands, menu_nodes: Query<(Entity, &Node)>) {
+fn teardown(
+    mut commands: Commands,
+    menu_nodes: Query<(Entity, &Node), Without<TransitionOverlay>>,
+) {
     for (e, _n) in menu_nodes.iter() {
         commands.entity(e).despawn();
     }
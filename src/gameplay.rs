@@ -3,19 +3,76 @@ use bevy::{
     sprite::collide_aabb::{collide, Collision},
 };
 
-use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::clock::GameTime;
+use crate::controls::PlayerInput;
+use crate::events::{
+    BallBounced, BallLost, BrickDestroyed, BrickHit, LevelCompleted, LifeLost, PaddleHit, WallHit,
+};
+use crate::playfield::Playfield;
+use crate::sprite_sheet::SpriteSheet;
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
 
 pub fn enter_system_set() -> SystemSet {
-    SystemSet::on_enter(crate::state::GameState::Level).with_system(setup_level)
+    SystemSet::on_enter(crate::state::GameState::Level)
+        .with_system(setup_level.label(GameplaySystem::Setup))
+}
+
+/// Order of the gameplay systems within a frame, so events are handled the frame they are sent.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameplaySystem {
+    /// Builds the level when it is entered.
+    Setup,
+    /// Moves the ball and paddle.
+    Movement,
+    /// Detects what the ball ran into and sends the matching events.
+    Collision,
+    /// Breaks bricks that were hit.
+    Bricks,
+    /// Takes lives for lost balls.
+    Lives,
+    /// Updates the score and lives.
+    Scoring,
+    /// Moves on to the next level once one is completed.
+    Progress,
 }
 
 pub fn update_system_set() -> SystemSet {
     SystemSet::on_update(crate::state::GameState::Level)
-        .with_system(ball_movement_system)
-        .with_system(paddle_movement_system)
-        .with_system(ball_collision_system)
-        .with_system(ball_boundary_system)
+        .with_system(ball_movement_system.label(GameplaySystem::Movement))
+        .with_system(paddle_movement_system.label(GameplaySystem::Movement))
+        .with_system(
+            ball_collision_system
+                .label(GameplaySystem::Collision)
+                .after(GameplaySystem::Movement),
+        )
+        .with_system(
+            ball_boundary_system
+                .label(GameplaySystem::Collision)
+                .after(GameplaySystem::Movement),
+        )
+        .with_system(
+            break_bricks_system
+                .label(GameplaySystem::Bricks)
+                .after(GameplaySystem::Collision),
+        )
+        .with_system(
+            lose_life_system
+                .label(GameplaySystem::Lives)
+                .after(GameplaySystem::Collision),
+        )
+        .with_system(
+            scoring_system
+                .label(GameplaySystem::Scoring)
+                .after(GameplaySystem::Bricks)
+                .after(GameplaySystem::Lives),
+        )
+        .with_system(
+            complete_level_system
+                .label(GameplaySystem::Progress)
+                .after(GameplaySystem::Scoring),
+        )
         .with_system(initial_pause_check)
+        .with_system(check_entities_system.before(crate::transition::TransitionSystem))
 }
 pub fn exit_system_set() -> SystemSet {
     SystemSet::on_exit(crate::state::GameState::Level).with_system(teardown_system)
@@ -24,23 +81,68 @@ pub fn paused_update_system_set() -> SystemSet {
     SystemSet::on_inactive_update(crate::state::GameState::Level)
 }
 pub fn initial_pause_check(
-    mut state: ResMut<State<crate::state::GameState>>,
-    mut is_new: ResMut<BrandNewLevel>,
+    mut transitions: ResMut<Transitions>,
+    is_new: Option<ResMut<BrandNewLevel>>,
+) {
+    if let Some(mut is_new) = is_new {
+        if is_new.0 {
+            transitions.push(crate::state::GameState::PauseMenu, TransitionStyle::Cut);
+            is_new.0 = false;
+        }
+    }
+}
+
+/// Gives up on the level for the error screen when its paddle or every ball has gone missing.
+///
+/// The other gameplay systems skip a frame they cannot play without these, so this is the
+/// one place that notices. Nothing is checked while a transition is on its way, as a level
+/// that is being left may already be partly torn down.
+fn check_entities_system(
+    mut transitions: ResMut<Transitions>,
+    mut error: ResMut<crate::error::GameError>,
+    paddle_query: Query<(), With<Paddle>>,
+    ball_query: Query<(), With<Ball>>,
 ) {
-    if is_new.0 {
-        state
-            .push(crate::state::GameState::PauseMenu)
-            .expect("Failed to enter pause menu");
-        is_new.0 = false;
+    if transitions.is_busy() {
+        return;
+    }
+    let paddles = paddle_query.iter().count();
+    if paddles != 1 {
+        error.report(
+            &mut transitions,
+            format!("Expected one paddle in the level, found {}", paddles),
+        );
+    } else if ball_query.is_empty() {
+        error.report(&mut transitions, "The level has no ball left");
     }
 }
 pub struct BrandNewLevel(bool);
 
+/// While on, balls bounce off the bottom of the playfield instead of being lost.
+#[derive(Default)]
+pub struct GodMode(pub bool);
+
+/// Source of randomness for gameplay, such as power-up drops.
+pub struct GameRng(pub rand::rngs::StdRng);
+
+impl GameRng {
+    /// Random numbers that come out the same on every run with the same `seed`.
+    pub fn from_seed(seed: u64) -> GameRng {
+        GameRng(rand::SeedableRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> GameRng {
+        GameRng(rand::SeedableRng::from_entropy())
+    }
+}
+
 fn teardown_system(
     mut commands: Commands,
     ball_query: Query<(&Ball, Entity)>,
     collider_query: Query<(&Collider, Entity)>,
-    ui_query: Query<(&Node, Entity)>,
+    ui_query: Query<(&Node, Entity), Without<TransitionOverlay>>,
 ) {
     for (_, be) in ball_query.iter() {
         commands.entity(be).despawn();
@@ -53,22 +155,34 @@ fn teardown_system(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup_level(
     mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    mut state: ResMut<State<crate::state::GameState>>,
-    atlases: ResMut<Assets<TextureAtlas>>,
+    mut transitions: ResMut<Transitions>,
+    mut error: ResMut<crate::error::GameError>,
+    sprite_sheet: Res<crate::sprite_sheet::GameSpriteSheet>,
+    sheets: Res<Assets<SpriteSheet>>,
     levels: Res<Vec<crate::level::Level>>,
     mut score: ResMut<Score>,
+    mut powerups: ResMut<crate::powerup::ActivePowerUps>,
 ) {
-    let atlas = crate::sprite_sheet::build_sprite_sheet(&mut asset_server, atlases);
+    let sheet = match sheets.get(&sprite_sheet.0) {
+        Some(sheet) => sheet,
+        None => {
+            error.report(&mut transitions, "The sprite sheet is not loaded");
+            return;
+        }
+    };
     if score.current_level >= levels.len() {
-        state.set(crate::state::GameState::GameOver);
+        transitions.set(crate::state::GameState::GameOver, TransitionStyle::Cut);
     } else {
         let new_level = &levels[score.current_level];
-        crate::level::add_bricks(&mut commands, &mut score, new_level, atlas.clone());
-        setup_ball_and_paddle(&mut commands, atlas);
-        setup_level_ui(&mut commands, asset_server, &*score);
+        let playfield = Playfield::from_level(new_level);
+        commands.insert_resource(playfield);
+        crate::level::add_bricks(&mut commands, &mut score, new_level, sheet);
+        setup_ball_and_paddle(&mut commands, &playfield, sheet);
+        score.combo = 0;
+        powerups.timers.clear();
         commands.insert_resource(BrandNewLevel(true));
     }
 }
@@ -77,10 +191,29 @@ pub struct Paddle {
     speed: f32,
 }
 
+impl Default for Paddle {
+    fn default() -> Paddle {
+        Paddle { speed: 500.0 }
+    }
+}
+
+impl Paddle {
+    /// Top speed in units per second.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+}
+
 #[derive(Component)]
 pub struct Ball {
-    velocity: Vec3,
-    size: Vec2,
+    pub velocity: Vec3,
+    pub size: Vec2,
+}
+
+/// A brick, with the hits it takes before it breaks.
+#[derive(Component)]
+pub struct Brick {
+    pub hits_left: u32,
 }
 
 #[derive(Component)]
@@ -103,146 +236,126 @@ pub struct Score {
     pub bricks_left: usize,
     pub points: i32,
     pub lives: i32,
+    /// Bricks broken since the ball last touched the paddle.
+    pub combo: u32,
 }
 impl Score {
     pub fn new() -> Score {
+        Score::with_lives(3)
+    }
+    pub fn with_lives(lives: i32) -> Score {
         Score {
             points: 0,
             bricks_left: 0,
-            lives: 3,
+            lives,
             current_level: 0,
+            combo: 0,
         }
     }
+    /// Points awarded per brick; grows by one for every `COMBO_STEP` bricks in the combo.
+    pub fn multiplier(&self) -> i32 {
+        (1 + self.combo / COMBO_STEP).min(MAX_MULTIPLIER) as i32
+    }
 }
-#[derive(Component)]
-pub struct PointsText;
-
-#[derive(Component)]
-pub struct LivesText;
-
-pub fn setup_level_ui(commands: &mut Commands, asset_server: Res<AssetServer>, score: &Score) {
-    let text_style = TextStyle {
-        font: asset_server.load("font/FiraSans-Light.ttf"),
-        font_size: 40.0,
-        color: Color::rgb(0.71, 0.8, 0.4),
-    };
+impl Default for Score {
+    fn default() -> Score {
+        Score::new()
+    }
+}
+const COMBO_STEP: u32 = 4;
+/// Speed the ball is served at.
+pub const BALL_SPEED: f32 = 400.0;
+const MAX_MULTIPLIER: u32 = 5;
+/// How far a ball caught at the very edge of the paddle is turned, as a share of its speed.
+const PADDLE_STEERING: f32 = 0.5;
+/// Smallest upward share of its speed a ball leaves the paddle with, so it never runs flat.
+const MIN_REBOUND_RISE: f32 = 0.3;
 
-    commands
-        .spawn_bundle(NodeBundle {
-            style: Style {
-                flex_direction: FlexDirection::Row,
-                align_self: AlignSelf::FlexEnd,
-                justify_content: JustifyContent::SpaceBetween,
-                size: Size {
-                    height: Val::Px(40.0),
-                    width: Val::Percent(100.0),
-                },
-
-                ..Default::default()
-            },
-            color: UiColor(Color::Rgba {
-                red: 1.,
-                green: 1.,
-                blue: 1.,
-                alpha: 0.0,
-            }),
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            parent
-                .spawn_bundle(TextBundle {
-                    text: Text::with_section(
-                        format!("Score: {}", score.points),
-                        text_style.clone(),
-                        TextAlignment {
-                            vertical: VerticalAlign::Top,
-                            horizontal: HorizontalAlign::Left,
-                        },
-                    ),
-                    style: Style {
-                        flex_grow: 0.0,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(PointsText);
-
-            parent
-                .spawn_bundle(TextBundle {
-                    text: Text::with_section(
-                        format!("Lives: {}", score.lives),
-                        text_style,
-                        TextAlignment {
-                            vertical: VerticalAlign::Top,
-                            horizontal: HorizontalAlign::Right,
-                        },
-                    ),
-                    style: Style {
-                        flex_grow: 0.0,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(LivesText);
-        });
+/// Velocity of a ball with `velocity` bouncing off the top of the paddle at `offset`, from -1 at
+/// the paddle's left edge to 1 at its right one.
+///
+/// The ball bounces back up as off a mirror, turned towards the side of the paddle it hit, so
+/// the player can aim. Its speed stays the same.
+pub fn paddle_rebound(velocity: Vec2, offset: f32) -> Vec2 {
+    let speed = velocity.length();
+    if speed == 0.0 {
+        return velocity;
+    }
+    let mirrored = Vec2::new(velocity.x, velocity.y.abs()) / speed;
+    let mut direction =
+        (mirrored + Vec2::X * offset.clamp(-1.0, 1.0) * PADDLE_STEERING).normalize();
+    if direction.y < MIN_REBOUND_RISE {
+        let across = (1.0 - MIN_REBOUND_RISE * MIN_REBOUND_RISE).sqrt();
+        direction = Vec2::new(direction.x.signum() * across, MIN_REBOUND_RISE);
+    }
+    direction * speed
 }
 
-pub fn setup_ball_and_paddle(commands: &mut Commands, atlas: Handle<TextureAtlas>) {
+pub fn setup_ball_and_paddle(commands: &mut Commands, playfield: &Playfield, sheet: &SpriteSheet) {
     // paddle
+    let paddle = sheet.index_or_default("paddle");
     commands
         .spawn_bundle(SpriteSheetBundle {
-            sprite: TextureAtlasSprite {
-                index: 1 as usize,
-                ..Default::default()
-            },
-
-            texture_atlas: atlas.clone(),
-            transform: Transform::from_xyz(0.0, (-SCREEN_HEIGHT + 40.) / 2., 0.0),
+            sprite: sheet.sprite_at(paddle),
+            texture_atlas: sheet.atlas.clone(),
+            transform: Transform::from_xyz(0.0, (-playfield.height + 40.) / 2., 0.0),
             ..Default::default()
         })
-        .insert(Paddle { speed: 500.0 })
+        .insert(Paddle::default())
         .insert(Collider::Paddle {
-            size: Vec2::new(162.0, 30.0),
+            size: sheet.hitbox(paddle).size(),
         });
     // ball
-    commands
-        .spawn_bundle(SpriteSheetBundle {
-            transform: Transform::from_xyz(0.0, -250.0, 1.0),
-            sprite: TextureAtlasSprite {
-                index: 0 as usize,
-                ..Default::default()
-            },
-            texture_atlas: atlas.clone(),
-            ..Default::default()
-        })
-        .insert(Ball {
-            velocity: 400.0 * Vec3::new(0.5, 0.5, 0.0).normalize(),
-            size: Vec2::new(30.0, 30.0),
-        });
+    spawn_ball(
+        commands,
+        sheet,
+        Vec3::new(0.0, -250.0, 1.0),
+        BALL_SPEED * Vec3::new(0.5, 0.5, 0.0).normalize(),
+    );
 }
 
-pub fn paddle_movement_system(
-    keyboard_input: ResMut<Input<KeyCode>>,
-    mut state: ResMut<State<crate::state::GameState>>,
-    time: Res<Time>,
-    mut query: Query<(&Paddle, &mut Transform)>,
-) {
-    let (paddle, mut transform) = query.single_mut();
-    let mut direction = 0.0;
-    if keyboard_input.pressed(KeyCode::Left) {
-        direction -= 1.0;
+pub fn spawn_ball(commands: &mut Commands, sheet: &SpriteSheet, position: Vec3, velocity: Vec3) {
+    let ball_index = sheet.index_or_default("ball");
+    let mut ball = commands.spawn_bundle(SpriteSheetBundle {
+        transform: Transform::from_translation(position),
+        sprite: sheet.sprite_at(ball_index),
+        texture_atlas: sheet.atlas.clone(),
+        ..Default::default()
+    });
+    ball.insert(Ball {
+        velocity,
+        size: sheet.hitbox(ball_index).size(),
+    });
+    if let Some(animation) = sheet.animation(ball_index) {
+        ball.insert(animation);
     }
+}
 
-    if keyboard_input.pressed(KeyCode::Right) {
-        direction += 1.0;
+pub fn paddle_movement_system(
+    input: Res<PlayerInput>,
+    mut transitions: ResMut<Transitions>,
+    playfield: Res<Playfield>,
+    time: Res<GameTime>,
+    mut query: Query<(&Paddle, &Collider, &mut Transform)>,
+) {
+    // a missing paddle is reported by `check_entities_system`
+    let (paddle, collider, mut transform) = match query.get_single_mut() {
+        Ok(paddle) => paddle,
+        Err(_) => return,
+    };
+    let mut direction = input.direction;
+    if let Some(target) = input.target {
+        // steer towards the target without exceeding the paddle's top speed
+        let max_step = paddle.speed * time.delta_seconds();
+        if max_step > 0.0 {
+            direction = ((target - transform.translation.x) / max_step).clamp(-1.0, 1.0);
+        }
     }
-    if keyboard_input.pressed(KeyCode::Space) || keyboard_input.pressed(KeyCode::P) {
-        state
-            .push(crate::state::GameState::PauseMenu)
-            .expect("Failed to open Pause Menu");
+    if input.pause {
+        transitions.push(crate::state::GameState::PauseMenu, TransitionStyle::Fade);
     }
 
-    let horizontal_limit = (SCREEN_WIDTH - 162.) / 2.;
+    let horizontal_limit = playfield.half_width() - collider.get_size().x / 2.;
     let translation = &mut transform.translation;
     // move the paddle horizontally
     translation.x += direction * paddle.speed * time.delta_seconds();
@@ -250,108 +363,480 @@ pub fn paddle_movement_system(
     translation.x = translation.x.min(horizontal_limit).max(-horizontal_limit);
 }
 
-pub fn ball_movement_system(mut ball_query: Query<(&Ball, &mut Transform)>, time: Res<Time>) {
-    let (ball, mut transform) = ball_query.single_mut();
-    transform.translation += ball.velocity * time.delta_seconds();
+pub fn ball_movement_system(
+    mut ball_query: Query<(&Ball, &mut Transform)>,
+    powerups: Res<crate::powerup::ActivePowerUps>,
+    transitions: Res<Transitions>,
+    time: Res<GameTime>,
+) {
+    // hold the ball still while the screen is changing
+    if transitions.is_busy() {
+        return;
+    }
+    for (ball, mut transform) in ball_query.iter_mut() {
+        transform.translation +=
+            ball.velocity * powerups.ball_speed_factor() * time.delta_seconds();
+    }
 }
 pub fn ball_boundary_system(
-    mut ball_query: Query<(&mut Ball, &mut Transform)>,
-    mut state: ResMut<State<crate::state::GameState>>,
-    mut lives_txt_query: Query<(&mut Text, &LivesText)>,
+    mut ball_query: Query<(Entity, &mut Ball, &mut Transform)>,
+    transitions: Res<Transitions>,
+    playfield: Res<Playfield>,
+    god_mode: Res<GodMode>,
+    mut wall_hits: EventWriter<WallHit>,
+    mut bounces: EventWriter<BallBounced>,
+    mut balls_lost: EventWriter<BallLost>,
+) {
+    // the ball is held still during transitions, so a lost ball would be counted every frame
+    if transitions.is_busy() {
+        return;
+    }
+    for (entity, mut ball, mut transform) in ball_query.iter_mut() {
+        let horizontal = playfield.half_width() - ball.size.x / 2.0;
+        let vertical = playfield.half_height() - ball.size.y / 2.0;
+        let mut normal = Vec2::ZERO;
+        if transform.translation.x < -horizontal || transform.translation.x > horizontal {
+            normal.x = -transform.translation.x.signum();
+            transform.translation.x = transform.translation.x.min(horizontal).max(-horizontal);
+            ball.velocity.x *= -1.0;
+        }
+        if transform.translation.y > vertical {
+            normal.y = -1.0;
+            transform.translation.y = vertical;
+            ball.velocity.y *= -1.0;
+        } else if transform.translation.y < -vertical && god_mode.0 {
+            normal.y = 1.0;
+            transform.translation.y = -vertical;
+            ball.velocity.y *= -1.0;
+        }
+        if normal != Vec2::ZERO {
+            wall_hits.send(WallHit {
+                position: transform.translation,
+                strength: ball.velocity.length() / BALL_SPEED,
+            });
+            bounces.send(BallBounced {
+                position: transform.translation,
+                normal: normal.normalize(),
+            });
+        }
+        if transform.translation.y < -vertical {
+            balls_lost.send(BallLost {
+                ball: entity,
+                position: transform.translation,
+            });
+        }
+    }
+}
+
+/// Takes a life for every lost ball, serving a new one or ending the game.
+fn lose_life_system(
+    mut commands: Commands,
+    mut balls_lost: EventReader<BallLost>,
+    mut lives_lost: EventWriter<LifeLost>,
+    mut transitions: ResMut<Transitions>,
     mut score: ResMut<Score>,
+    mut ball_query: Query<(Entity, &mut Ball, &mut Transform)>,
 ) {
-    let horizontal = SCREEN_WIDTH / 2. - 15.0;
-    let vertical = (SCREEN_HEIGHT - 30.) / 2.;
-    let (mut ball, mut transform) = ball_query.single_mut();
-    if transform.translation.x < -horizontal || transform.translation.x > horizontal {
-        transform.translation.x = transform.translation.x.min(horizontal).max(-horizontal);
-        ball.velocity.x *= -1.0;
-    }
-    if transform.translation.y > vertical {
-        transform.translation.y = vertical;
-        ball.velocity.y *= -1.0;
-    }
-    if transform.translation.y < -vertical {
+    let mut balls_left = ball_query.iter().count();
+    for lost in balls_lost.iter() {
+        // with more than one ball in play, a lost ball is simply removed
+        if balls_left > 1 {
+            commands.entity(lost.ball).despawn();
+            balls_left -= 1;
+            continue;
+        }
         score.lives -= 1;
+        lives_lost.send(LifeLost {
+            lives_left: score.lives,
+        });
         if score.lives <= 0 {
-            state
-                .set(crate::state::GameState::MainMenu)
-                .expect("Failed to open main menu");
+            transitions.set(crate::state::GameState::MainMenu, TransitionStyle::Fade);
         } else {
-            let (mut lives_text, _) = lives_txt_query.get_single_mut().unwrap();
-            lives_text.sections[0].value = format!("Lives: {}", &score.lives);
-            ball.velocity = 400.0 * Vec3::new(0.5, 0.5, 0.0).normalize();
-
-            *transform = Transform::from_xyz(0.0, -250.0, 1.0);
-            state
-                .push(crate::state::GameState::PauseMenu)
-                .expect("Failed to open pause menu");
+            for (_, mut ball, mut transform) in ball_query.iter_mut() {
+                ball.velocity = BALL_SPEED * Vec3::new(0.5, 0.5, 0.0).normalize();
+                *transform = Transform::from_xyz(0.0, -250.0, 1.0);
+            }
+            transitions.push(crate::state::GameState::PauseMenu, TransitionStyle::Fade);
         }
     }
 }
+
 pub fn ball_collision_system(
-    mut commands: Commands,
     mut ball_query: Query<(&mut Ball, &Transform)>,
-    mut state: ResMut<State<crate::state::GameState>>,
-    mut score: ResMut<Score>,
-    mut points_txt_query: Query<(&mut Text, &PointsText)>,
+    time: Res<GameTime>,
+    mut brick_hits: EventWriter<BrickHit>,
+    mut paddle_hits: EventWriter<PaddleHit>,
+    mut bounces: EventWriter<BallBounced>,
     collider_query: Query<(Entity, &Collider, &Transform)>,
 ) {
-    let (mut ball, ball_transform) = ball_query.single_mut();
-    let ball_size = ball.size;
-    let velocity = &mut ball.velocity;
-    let (mut points_text, _) = points_txt_query.get_single_mut().unwrap();
-
-    // check collision with walls
-    for (collider_entity, collider, transform) in collider_query.iter() {
-        let collision = collide(
-            ball_transform.translation,
-            ball_size,
-            transform.translation,
-            collider.get_size(),
-        );
-        if let Some(collision) = collision {
-            // scorable colliders should be despawned and increment the scoreboard on collision
-            if let Collider::Scorable { size: _ } = *collider {
-                commands.entity(collider_entity).despawn();
-                score.points += 1;
-                score.bricks_left = score.bricks_left.saturating_sub(1);
-                if score.bricks_left == 0 {
-                    score.current_level += 1;
-                    state.restart();
+    // nothing has moved while the clock stands still, so the last contacts would repeat
+    if time.delta().is_zero() {
+        return;
+    }
+    for (mut ball, ball_transform) in ball_query.iter_mut() {
+        let ball_size = ball.size;
+        let velocity = &mut ball.velocity;
+        let strength = velocity.length() / BALL_SPEED;
+
+        // check collision with walls
+        for (collider_entity, collider, transform) in collider_query.iter() {
+            let collision = collide(
+                ball_transform.translation,
+                ball_size,
+                transform.translation,
+                collider.get_size(),
+            );
+            if let Some(collision) = collision {
+                match *collider {
+                    Collider::Scorable { size } => brick_hits.send(BrickHit {
+                        brick: collider_entity,
+                        position: transform.translation,
+                        size,
+                        strength,
+                    }),
+                    Collider::Paddle { size: _ } => paddle_hits.send(PaddleHit {
+                        position: ball_transform.translation,
+                        strength,
+                    }),
                 }
-                points_text.as_mut().sections[0].value = format!("Score: {}", score.points);
-            }
 
-            // reflect the ball when it collides
-            let mut reflect_x = false;
-            let mut reflect_y = false;
-
-            // only reflect if the ball's velocity is going in the opposite direction of the
-            // collision
-            match collision {
-                Collision::Left => reflect_x = velocity.x > 0.0,
-                Collision::Right => reflect_x = velocity.x < 0.0,
-                Collision::Top => reflect_y = velocity.y < 0.0,
-                Collision::Bottom => reflect_y = velocity.y > 0.0,
-                Collision::Inside => {
-                    reflect_y = true;
-                    reflect_x = true
+                // reflect the ball when it collides
+                let mut reflect_x = false;
+                let mut reflect_y = false;
+
+                // only reflect if the ball's velocity is going in the opposite direction of the
+                // collision
+                match collision {
+                    Collision::Left => reflect_x = velocity.x > 0.0,
+                    Collision::Right => reflect_x = velocity.x < 0.0,
+                    Collision::Top => reflect_y = velocity.y < 0.0,
+                    Collision::Bottom => reflect_y = velocity.y > 0.0,
+                    Collision::Inside => {
+                        reflect_y = true;
+                        reflect_x = true
+                    }
                 }
-            }
 
-            // reflect velocity on the x-axis if we hit something on the x-axis
-            if reflect_x {
-                velocity.x = -velocity.x;
-            }
+                if reflect_x || reflect_y {
+                    let normal = Vec2::new(
+                        if reflect_x { -velocity.x.signum() } else { 0.0 },
+                        if reflect_y { -velocity.y.signum() } else { 0.0 },
+                    );
+                    bounces.send(BallBounced {
+                        position: ball_transform.translation,
+                        normal: normal.normalize(),
+                    });
+                }
 
-            // reflect velocity on the y-axis if we hit something on the y-axis
-            if reflect_y {
-                velocity.y = -velocity.y;
+                // reflect velocity on the x-axis if we hit something on the x-axis
+                if reflect_x {
+                    velocity.x = -velocity.x;
+                }
+
+                // reflect velocity on the y-axis if we hit something on the y-axis
+                if reflect_y {
+                    velocity.y = -velocity.y;
+                }
+
+                // the top of the paddle turns the ball towards the side it hit
+                if let (Collider::Paddle { size }, Collision::Top) = (collider, collision) {
+                    if reflect_y {
+                        let offset = (ball_transform.translation.x - transform.translation.x)
+                            / (size.x / 2.0);
+                        let rebound = paddle_rebound(velocity.truncate(), offset);
+                        velocity.x = rebound.x;
+                        velocity.y = rebound.y;
+                    }
+                }
+
+                // break if this collide is on a solid, otherwise continue check whether a solid is
+                // also in collision
             }
+        }
+    }
+}
+
+/// A brick and the sprite it is drawn with, if any.
+type BrickSprite<'a> = (
+    &'a mut Brick,
+    Option<&'a Handle<TextureAtlas>>,
+    Option<&'a TextureAtlasSprite>,
+);
+
+/// Bricks break once they have taken all their hits.
+fn break_bricks_system(
+    mut commands: Commands,
+    mut brick_hits: EventReader<BrickHit>,
+    mut destroyed: EventWriter<BrickDestroyed>,
+    mut brick_query: Query<BrickSprite>,
+) {
+    for hit in brick_hits.iter() {
+        let (mut brick, atlas, sprite) = match brick_query.get_mut(hit.brick) {
+            Ok(brick) => brick,
+            Err(_) => continue,
+        };
+        // the same brick can only break once
+        if brick.hits_left == 0 {
+            continue;
+        }
+        brick.hits_left -= 1;
+        if brick.hits_left > 0 {
+            continue;
+        }
+        commands.entity(hit.brick).despawn();
+        destroyed.send(BrickDestroyed {
+            position: hit.position,
+            size: hit.size,
+            sprite: atlas.cloned().zip(sprite.map(|sprite| sprite.index)),
+        });
+    }
+}
 
-            // break if this collide is on a solid, otherwise continue check whether a solid is
-            // also in collision
+/// Awards points for destroyed bricks and keeps track of the combo.
+fn scoring_system(
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut paddle_hits: EventReader<PaddleHit>,
+    mut lives_lost: EventReader<LifeLost>,
+    mut completed: EventWriter<LevelCompleted>,
+    mut score: ResMut<Score>,
+) {
+    // touching the paddle or losing the ball ends the combo; every event has to be read, or it
+    // would still count on the next frame
+    let paddle_touched = paddle_hits.iter().count() > 0;
+    let ball_lost = lives_lost.iter().count() > 0;
+    if paddle_touched || ball_lost {
+        score.combo = 0;
+    }
+    for _ in destroyed.iter() {
+        if score.bricks_left == 0 {
+            continue;
         }
+        score.points += score.multiplier();
+        score.combo += 1;
+        score.bricks_left -= 1;
+        if score.bricks_left == 0 {
+            completed.send(LevelCompleted {
+                level: score.current_level,
+            });
+        }
+    }
+}
+
+fn complete_level_system(
+    mut completed: EventReader<LevelCompleted>,
+    mut transitions: ResMut<Transitions>,
+    mut score: ResMut<Score>,
+) {
+    for _ in completed.iter() {
+        score.current_level += 1;
+        transitions.restart(TransitionStyle::Slide);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::Events;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(Score::new())
+            .init_resource::<Transitions>();
+        crate::events::add_events(&mut app);
+        app
+    }
+
+    fn sent<T: Send + Sync + 'static>(app: &App) -> usize {
+        let events = app.world.resource::<Events<T>>();
+        events.get_reader().iter(events).count()
+    }
+
+    fn destroyed() -> BrickDestroyed {
+        BrickDestroyed {
+            position: Vec3::ZERO,
+            size: Vec2::ONE,
+            sprite: None,
+        }
+    }
+
+    #[test]
+    fn hit_bricks_break_once() {
+        let mut app = app();
+        app.add_system(break_bricks_system);
+        let brick = app.world.spawn().insert(Brick { hits_left: 1 }).id();
+        let hit = || BrickHit {
+            brick,
+            position: Vec3::ZERO,
+            size: Vec2::ONE,
+            strength: 1.0,
+        };
+        app.world.resource_mut::<Events<BrickHit>>().send(hit());
+        app.update();
+        assert!(app.world.get_entity(brick).is_none());
+        assert_eq!(sent::<BrickDestroyed>(&app), 1);
+
+        app.world.resource_mut::<Events<BrickHit>>().send(hit());
+        app.update();
+        assert_eq!(sent::<BrickDestroyed>(&app), 1);
+    }
+
+    #[test]
+    fn tough_bricks_survive_a_hit() {
+        let mut app = app();
+        app.add_system(break_bricks_system);
+        let brick = app.world.spawn().insert(Brick { hits_left: 2 }).id();
+        let hit = || BrickHit {
+            brick,
+            position: Vec3::ZERO,
+            size: Vec2::ONE,
+            strength: 1.0,
+        };
+        app.world.resource_mut::<Events<BrickHit>>().send(hit());
+        app.update();
+        assert_eq!(app.world.get::<Brick>(brick).unwrap().hits_left, 1);
+        assert_eq!(sent::<BrickDestroyed>(&app), 0);
+
+        // hit twice in one frame, say by two balls, it still breaks only once
+        let mut hits = app.world.resource_mut::<Events<BrickHit>>();
+        hits.send(hit());
+        hits.send(hit());
+        app.update();
+        assert!(app.world.get_entity(brick).is_none());
+        assert_eq!(sent::<BrickDestroyed>(&app), 1);
+    }
+
+    #[test]
+    fn destroyed_bricks_score_and_build_the_combo() {
+        let mut app = app();
+        app.add_system(scoring_system);
+        app.world.resource_mut::<Score>().bricks_left = 10;
+        for _ in 0..COMBO_STEP + 1 {
+            app.world
+                .resource_mut::<Events<BrickDestroyed>>()
+                .send(destroyed());
+        }
+        app.update();
+        let score = app.world.resource::<Score>();
+        assert_eq!(score.points, COMBO_STEP as i32 + 2);
+        assert_eq!(score.combo, COMBO_STEP + 1);
+        assert_eq!(score.bricks_left, 5);
+        assert_eq!(sent::<LevelCompleted>(&app), 0);
+    }
+
+    #[test]
+    fn touching_the_paddle_ends_the_combo() {
+        let mut app = app();
+        app.add_system(scoring_system);
+        app.world.resource_mut::<Score>().combo = 6;
+        app.world
+            .resource_mut::<Events<PaddleHit>>()
+            .send(PaddleHit {
+                position: Vec3::ZERO,
+                strength: 1.0,
+            });
+        app.update();
+        assert_eq!(app.world.resource::<Score>().combo, 0);
+    }
+
+    #[test]
+    fn the_paddle_turns_the_ball_towards_the_side_it_hit() {
+        let falling = Vec2::new(0.0, -BALL_SPEED);
+        assert_eq!(paddle_rebound(falling, 0.0), Vec2::new(0.0, BALL_SPEED));
+        let right = paddle_rebound(falling, 0.5);
+        assert!(right.x > 0.0 && right.y > 0.0);
+        assert!((right.length() - BALL_SPEED).abs() < 1e-3);
+        assert_eq!(paddle_rebound(falling, -0.5).x, -right.x);
+        // even a ball coming in flat leaves steeply enough
+        let flat = paddle_rebound(Vec2::new(BALL_SPEED, -1.0), 1.0);
+        assert!(flat.y >= MIN_REBOUND_RISE * BALL_SPEED - 1e-3);
+    }
+
+    #[test]
+    fn destroying_the_last_brick_completes_the_level() {
+        let mut app = app();
+        app.add_system(scoring_system.label(GameplaySystem::Scoring))
+            .add_system(complete_level_system.after(GameplaySystem::Scoring));
+        app.world.resource_mut::<Score>().bricks_left = 1;
+        app.world
+            .resource_mut::<Events<BrickDestroyed>>()
+            .send(destroyed());
+        app.update();
+        let score = app.world.resource::<Score>();
+        assert_eq!(score.bricks_left, 0);
+        assert_eq!(score.current_level, 1);
+        assert_eq!(sent::<LevelCompleted>(&app), 1);
+        assert!(app.world.resource::<Transitions>().is_busy());
+    }
+
+    #[test]
+    fn bouncing_off_a_wall_reports_its_normal() {
+        let mut app = app();
+        app.init_resource::<Playfield>()
+            .init_resource::<GodMode>()
+            .add_system(ball_boundary_system);
+        app.world
+            .spawn()
+            .insert(Ball {
+                velocity: Vec3::new(BALL_SPEED, BALL_SPEED, 0.0),
+                size: Vec2::ONE,
+            })
+            .insert(Transform::from_xyz(600.0, 400.0, 1.0));
+        app.update();
+        let events = app.world.resource::<Events<BallBounced>>();
+        let normals: Vec<Vec2> = events.get_reader().iter(events).map(|b| b.normal).collect();
+        assert_eq!(normals, vec![Vec2::new(-1.0, -1.0).normalize()]);
+        assert_eq!(sent::<WallHit>(&app), 1);
+    }
+
+    #[test]
+    fn losing_the_ball_takes_a_life_and_serves_again() {
+        let mut app = app();
+        app.add_system(lose_life_system);
+        let ball = app
+            .world
+            .spawn()
+            .insert(Ball {
+                velocity: Vec3::new(0.0, -BALL_SPEED, 0.0),
+                size: Vec2::ONE,
+            })
+            .insert(Transform::from_xyz(100.0, -400.0, 1.0))
+            .id();
+        app.world.resource_mut::<Events<BallLost>>().send(BallLost {
+            ball,
+            position: Vec3::ZERO,
+        });
+        app.update();
+        assert_eq!(app.world.resource::<Score>().lives, 2);
+        assert_eq!(sent::<LifeLost>(&app), 1);
+        let transform = app.world.get::<Transform>(ball).unwrap();
+        assert_eq!(transform.translation, Vec3::new(0.0, -250.0, 1.0));
+        assert!(app.world.get::<Ball>(ball).unwrap().velocity.y > 0.0);
+    }
+
+    #[test]
+    fn losing_one_of_two_balls_keeps_the_life() {
+        let mut app = app();
+        app.add_system(lose_life_system);
+        let mut balls = (0..2).map(|_| {
+            app.world
+                .spawn()
+                .insert(Ball {
+                    velocity: Vec3::new(0.0, -BALL_SPEED, 0.0),
+                    size: Vec2::ONE,
+                })
+                .insert(Transform::default())
+                .id()
+        });
+        let (lost, kept) = (balls.next().unwrap(), balls.next().unwrap());
+        app.world.resource_mut::<Events<BallLost>>().send(BallLost {
+            ball: lost,
+            position: Vec3::ZERO,
+        });
+        app.update();
+        assert_eq!(app.world.resource::<Score>().lives, 3);
+        assert!(app.world.get_entity(lost).is_none());
+        assert!(app.world.get_entity(kept).is_some());
+        assert_eq!(sent::<LifeLost>(&app), 0);
     }
 }
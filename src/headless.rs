@@ -0,0 +1,71 @@
+//! Gameplay without a window, renderer or audio, for scripted runs and tests.
+//!
+//! The app is [`CorePlugin`] on its own, with stand-ins for the input and window resources of
+//! `DefaultPlugins`. It starts straight in the first level and every `update` advances the game
+//! by exactly [`TICK`], so the same config, seed and input always play out the same way. Menus,
+//! the HUD, effects and audio are left out, and `Settings` are the defaults rather than the
+//! player's. Games are recorded, but only kept in `Recorder::last`. With
+//! `BlocksConfig::autopilot` set the AI plays, otherwise only the input a test sends does.
+
+use anyhow::{bail, Context};
+use bevy::{asset::AssetPlugin, input::keyboard::KeyboardInput, prelude::*};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::clock::GameTime;
+use crate::level::Level;
+use crate::replay::Recorder;
+use crate::settings::Settings;
+use crate::sprite_sheet::{GameSpriteSheet, SpriteSheet};
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, Theme};
+use crate::{BlocksConfig, CorePlugin};
+
+/// Length of every headless frame.
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Builds a headless game from `config`, ready to be stepped with `App::update`.
+///
+/// Levels and the sprite sheet are read up front, so the first update already plays.
+pub fn app(config: BlocksConfig) -> anyhow::Result<App> {
+    let path = Path::new("assets").join(&config.sprite_sheet);
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (mut sheet, atlas, _) = SpriteSheet::from_ron(&bytes)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin)
+        .add_asset::<TextureAtlas>()
+        .insert_resource(State::new(GameState::Level))
+        .insert_resource(GameTime::fixed(TICK))
+        .insert_resource(Settings::default())
+        .insert_resource(ActiveTheme {
+            selected: String::new(),
+            handle: Handle::default(),
+            theme: Theme::default(),
+            font: Handle::default(),
+        })
+        .insert_resource(Windows::default())
+        .init_resource::<Input<KeyCode>>()
+        .init_resource::<Input<MouseButton>>()
+        .add_event::<KeyboardInput>()
+        .add_event::<CursorMoved>()
+        .init_resource::<Recorder>()
+        .add_plugin(CorePlugin {
+            config: config.clone(),
+        });
+
+    let levels = app.world.resource::<Vec<Level>>();
+    if levels.is_empty() {
+        bail!("No levels found in {:?}", config.levels);
+    }
+    if config.start_level >= levels.len() {
+        bail!("There is no level {}", config.start_level + 1);
+    }
+    sheet.atlas = app.world.resource_mut::<Assets<TextureAtlas>>().add(atlas);
+    let sheet = app.world.resource_mut::<Assets<SpriteSheet>>().add(sheet);
+    app.insert_resource(GameSpriteSheet(sheet));
+    Ok(app)
+}
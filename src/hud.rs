@@ -0,0 +1,323 @@
+use bevy::core::Stopwatch;
+use bevy::prelude::*;
+
+use crate::clock::GameTime;
+use crate::gameplay::{Ball, Score};
+use crate::locale::{Locale, LocalizedText};
+use crate::playfield::Playfield;
+use crate::powerup::ActivePowerUps;
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedText};
+
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Level).with_system(setup_hud)
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Level)
+        .with_system(tick_level_clock)
+        .with_system(update_score_fields)
+        .with_system(update_clock_field)
+        .with_system(update_powerup_field)
+        .with_system(update_life_icons)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Level).with_system(teardown)
+}
+
+/// Time spent playing the current level, in game time; it does not advance while the game is
+/// paused, and keeps up with replays that are fast-forwarded.
+#[derive(Default)]
+pub struct LevelClock(pub Stopwatch);
+
+#[derive(Component, Clone, Copy, PartialEq)]
+enum HudField {
+    Level,
+    Bricks,
+    Clock,
+    Points,
+    Combo,
+    PowerUps,
+}
+
+/// One ball sprite per remaining life, drawn in the top right corner of the playfield.
+#[derive(Component)]
+struct LifeIcon;
+
+fn setup_hud(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    levels: Res<Vec<crate::level::Level>>,
+    score: Res<Score>,
+    mut clock: ResMut<LevelClock>,
+) {
+    let level = match levels.get(score.current_level) {
+        Some(level) => level,
+        None => return,
+    };
+    clock.0.reset();
+    let text_style = theme.text_style(ThemedText::Hud);
+    let level_label = if level.name.is_empty() {
+        LocalizedText::new("hud.level").with_arg("number", score.current_level + 1)
+    } else {
+        LocalizedText::new("hud.level_named")
+            .with_arg("number", score.current_level + 1)
+            .with_arg("name", &level.name)
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_self: AlignSelf::FlexEnd,
+                justify_content: JustifyContent::SpaceBetween,
+                size: Size {
+                    height: Val::Px(40.0),
+                    width: Val::Percent(100.0),
+                },
+                // leave room on the right for the life icons
+                padding: Rect {
+                    right: Val::Px(130.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for (field, label) in [
+                (HudField::Level, level_label),
+                (HudField::Bricks, LocalizedText::new("hud.bricks")),
+                (HudField::Clock, LocalizedText::new("hud.time")),
+                (HudField::Points, LocalizedText::new("hud.score")),
+                (HudField::Combo, LocalizedText::new("hud.combo")),
+            ] {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "",
+                            text_style.clone(),
+                            TextAlignment {
+                                vertical: VerticalAlign::Top,
+                                horizontal: HorizontalAlign::Left,
+                            },
+                        ),
+                        style: Style {
+                            flex_grow: 0.0,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .insert(field)
+                    .insert(label)
+                    .insert(ThemedText::Hud);
+            }
+        });
+
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "",
+                theme.text_style(ThemedText::Small),
+                TextAlignment {
+                    vertical: VerticalAlign::Top,
+                    horizontal: HorizontalAlign::Right,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(45.0),
+                    right: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(HudField::PowerUps)
+        .insert(ThemedText::Small);
+}
+
+fn tick_level_clock(time: Res<GameTime>, mut clock: ResMut<LevelClock>) {
+    clock.0.tick(time.delta());
+}
+
+fn update_score_fields(
+    score: Res<Score>,
+    new_fields: Query<(), Added<HudField>>,
+    mut field_query: Query<(&HudField, &mut LocalizedText, &mut Visibility)>,
+) {
+    if !score.is_changed() && new_fields.is_empty() {
+        return;
+    }
+    for (field, mut label, mut visibility) in field_query.iter_mut() {
+        let updated = match field {
+            HudField::Bricks => {
+                LocalizedText::new("hud.bricks").with_arg("count", score.bricks_left)
+            }
+            HudField::Points => LocalizedText::new("hud.score").with_arg("points", score.points),
+            HudField::Combo => {
+                let multiplier = score.multiplier();
+                visibility.is_visible = multiplier > 1;
+                LocalizedText::new("hud.combo").with_arg("multiplier", multiplier)
+            }
+            _ => continue,
+        };
+        if *label != updated {
+            *label = updated;
+        }
+    }
+}
+
+fn update_clock_field(
+    clock: Res<LevelClock>,
+    mut field_query: Query<(&HudField, &mut LocalizedText)>,
+) {
+    let seconds = clock.0.elapsed().as_secs();
+    let updated = LocalizedText::new("hud.time")
+        .with_arg("minutes", seconds / 60)
+        .with_arg("seconds", format!("{:02}", seconds % 60));
+    for (field, mut label) in field_query.iter_mut() {
+        if *field == HudField::Clock && *label != updated {
+            *label = updated.clone();
+        }
+    }
+}
+
+fn update_powerup_field(
+    active: Res<ActivePowerUps>,
+    locale: Res<Locale>,
+    mut field_query: Query<(&HudField, &mut Text)>,
+) {
+    if !active.is_changed() && !locale.is_changed() {
+        return;
+    }
+    let value = active
+        .timers
+        .iter()
+        .map(|(kind, timer)| {
+            let left = timer.duration().as_secs_f32() - timer.elapsed_secs();
+            locale.format(
+                "hud.powerup",
+                &[
+                    ("name", locale.get(kind.name_key())),
+                    ("seconds", format!("{:.0}", left.ceil())),
+                ],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    for (field, mut text) in field_query.iter_mut() {
+        if *field == HudField::PowerUps && text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Redraws the life icons when the lives change, or when a ball to copy them from appears.
+fn update_life_icons(
+    mut commands: Commands,
+    score: Res<Score>,
+    playfield: Res<Playfield>,
+    new_balls: Query<(), Added<Ball>>,
+    ball_query: Query<(&Handle<TextureAtlas>, &TextureAtlasSprite), With<Ball>>,
+    icon_query: Query<Entity, With<LifeIcon>>,
+) {
+    if !score.is_changed() && new_balls.is_empty() {
+        return;
+    }
+    let lives = score.lives.max(0) as usize;
+    if icon_query.iter().count() == lives {
+        return;
+    }
+    let (atlas, ball_sprite) = match ball_query.iter().next() {
+        Some(ball) => ball,
+        None => return,
+    };
+    for icon in icon_query.iter() {
+        commands.entity(icon).despawn();
+    }
+    for i in 0..lives {
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                texture_atlas: atlas.clone(),
+                sprite: TextureAtlasSprite {
+                    index: ball_sprite.index,
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation: Vec3::new(
+                        playfield.half_width() - 20.0 - i as f32 * 35.0,
+                        playfield.half_height() - 20.0,
+                        5.0,
+                    ),
+                    scale: Vec3::splat(0.8),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(LifeIcon);
+    }
+}
+
+fn teardown(mut commands: Commands, icon_query: Query<Entity, With<LifeIcon>>) {
+    for icon in icon_query.iter() {
+        commands.entity(icon).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(Score::new())
+            .init_resource::<Playfield>()
+            .insert_resource(GameTime::fixed(Duration::from_millis(500)))
+            .add_system_to_stage(CoreStage::First, crate::clock::update_game_time_system)
+            .init_resource::<LevelClock>();
+        app
+    }
+
+    fn icons(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<LifeIcon>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    #[test]
+    fn life_icons_follow_the_lives() {
+        let mut app = app();
+        app.add_system(update_life_icons);
+        app.world
+            .spawn()
+            .insert(Ball {
+                velocity: Vec3::ZERO,
+                size: Vec2::ONE,
+            })
+            .insert(Handle::<TextureAtlas>::default())
+            .insert(TextureAtlasSprite::default());
+        app.update();
+        assert_eq!(icons(&mut app), 3);
+        app.world.resource_mut::<Score>().lives = 1;
+        app.update();
+        assert_eq!(icons(&mut app), 1);
+    }
+
+    #[test]
+    fn the_level_clock_runs_on_game_time() {
+        let mut app = app();
+        app.add_system(tick_level_clock);
+        app.update();
+        app.update();
+        let clock = app.world.resource::<LevelClock>();
+        assert_eq!(clock.0.elapsed(), Duration::from_secs(1));
+    }
+}
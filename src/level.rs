@@ -1,14 +1,48 @@
 use bevy::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
 
+use crate::sprite_sheet::SpriteSheet;
+
+/// Maps a character in `Level::rows` to the sprite its bricks are drawn with.
+///
+/// The sprite is given either by its name in the sprite sheet or by its atlas index; the name
+/// wins if both are set.
 #[derive(Deserialize, Debug, Clone, Component)]
 pub struct Block {
     pub name: char,
-    pub sprite_number: usize,
+    #[serde(default)]
+    pub sprite: String,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub sprite_number: Option<usize>,
+    /// Hits the brick takes before it breaks.
+    #[serde(default = "one_hit")]
+    pub hits: u32,
+}
+
+fn one_hit() -> u32 {
+    1
+}
+
+/// Lets level files write `sprite_number: 2` rather than `sprite_number: Some(2)`.
+fn deserialize_some<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    usize::deserialize(deserializer).map(Some)
+}
+
+impl Block {
+    pub fn sprite_index(&self, sheet: &SpriteSheet) -> Option<usize> {
+        if self.sprite.is_empty() {
+            self.sprite_number
+        } else {
+            sheet.index(&self.sprite)
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Component)]
 pub struct Level {
+    #[serde(default)]
+    pub name: String,
     pub level_width: usize,
     pub level_height: usize,
     pub ball_velocity: f32,
@@ -17,46 +51,123 @@ pub struct Level {
     pub block_height: usize,
     pub blocks: Vec<Block>,
     pub rows: Vec<String>,
+    /// Music played during the level, relative to the assets folder; the default track when empty.
+    #[serde(default)]
+    pub music: String,
+}
+
+/// Descriptions of the level files that could not be read, for the loading screen to show.
+pub struct LevelErrors(pub Vec<String>);
+
+/// Where the game's levels come from.
+#[derive(Debug, Clone)]
+pub enum LevelSource {
+    /// Every `.ron` file in a folder, in file name order.
+    Directory(PathBuf),
+    /// The given files, in order.
+    Files(Vec<PathBuf>),
+    /// Levels already in memory.
+    Levels(Vec<Level>),
+}
+
+impl LevelSource {
+    /// Reads the levels, skipping any file that cannot be read or parsed.
+    ///
+    /// Returns the levels along with a description of every file that was skipped.
+    pub fn load(&self) -> (Vec<Level>, Vec<String>) {
+        match self {
+            LevelSource::Directory(dir) => {
+                let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+                    Ok(entries) => entries
+                        .filter_map(Result::ok)
+                        .map(|entry| entry.path())
+                        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+                        .collect(),
+                    Err(e) => {
+                        let error = format!("Failed to read levels from {}: {}", dir.display(), e);
+                        warn!("{}", error);
+                        return (Vec::new(), vec![error]);
+                    }
+                };
+                paths.sort();
+                load_levels(&paths)
+            }
+            LevelSource::Files(paths) => load_levels(paths),
+            LevelSource::Levels(levels) => (levels.clone(), Vec::new()),
+        }
+    }
 }
 
-pub fn add_bricks(commands: &mut Commands, score: &mut crate::gameplay::Score, level: &Level, atlas: Handle<TextureAtlas>) {
-    let mut block_map: std::collections::HashMap<char, usize> = std::collections::HashMap::new();
+fn load_levels(paths: &[PathBuf]) -> (Vec<Level>, Vec<String>) {
+    let mut levels = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match load_level(path) {
+            Ok(level) => levels.push(level),
+            Err(error) => {
+                warn!("{}", error);
+                errors.push(error);
+            }
+        }
+    }
+    (levels, errors)
+}
+
+fn load_level(path: &Path) -> Result<Level, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    ron::de::from_reader(file).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+pub fn add_bricks(commands: &mut Commands, score: &mut crate::gameplay::Score, level: &Level, sheet: &SpriteSheet) {
+    let mut block_map: std::collections::HashMap<char, (usize, u32)> = std::collections::HashMap::new();
     for block in &level.blocks {
-        block_map.insert(block.name, block.sprite_number);
+        match block.sprite_index(sheet) {
+            Some(index) => {
+                block_map.insert(block.name, (index, block.hits.max(1)));
+            }
+            None => warn!("Block {:?} has no sprite in the sprite sheet", block.name),
+        }
     }
+    let block_size = Vec2::new(level.block_width as f32, level.block_height as f32);
     let left = -(level.level_width as i32 - level.block_width as i32) / 2;
-    let top = (crate::SCREEN_HEIGHT as i32 - level.block_height as i32) / 2 - 40;
+    let top = (level.level_height as i32 - level.block_height as i32) / 2 - 40;
     let mut current_row = 0.0;
     for row in &level.rows {
         let mut current_col = 0.0;
         for c in row.chars() {
-            if let Some(num) = block_map.get(&c) {
+            if let Some((num, hits)) = block_map.get(&c) {
                 if c != ' ' {
                     score.bricks_left += 1;
-                    commands
-                        .spawn_bundle(SpriteSheetBundle {
-                            texture_atlas: atlas.clone(),
-                            sprite: TextureAtlasSprite {
-                                index: *num as usize,
-                                ..Default::default()
-                            },
-                            transform: Transform {
-                                translation: Vec3::new(
-                                    left as f32
-                                        + current_col
-                                            * (level.block_margin + level.block_width) as f32,
-                                    top as f32
-                                        - current_row
-                                            * (level.block_margin + level.block_height) as f32,
-                                    0.0,
-                                ),
-                                ..Default::default()
-                            },
+                    // stretch the sprite so its hitbox fills the level's block size
+                    let mut sprite = sheet.sprite_at(*num);
+                    let scale = block_size / sheet.hitbox(*num).size();
+                    if scale != Vec2::ONE {
+                        sprite.custom_size = Some(sheet.size(*num) * scale);
+                    }
+                    let mut brick = commands.spawn_bundle(SpriteSheetBundle {
+                        texture_atlas: sheet.atlas.clone(),
+                        sprite,
+                        transform: Transform {
+                            translation: Vec3::new(
+                                left as f32
+                                    + current_col
+                                        * (level.block_margin + level.block_width) as f32,
+                                top as f32
+                                    - current_row
+                                        * (level.block_margin + level.block_height) as f32,
+                                0.0,
+                            ),
                             ..Default::default()
-                        })
-                        .insert(crate::gameplay::Collider::Scorable {
-                            size: Vec2::new(level.block_width as f32, level.block_height as f32),
-                        });
+                        },
+                        ..Default::default()
+                    });
+                    brick
+                        .insert(crate::gameplay::Collider::Scorable { size: block_size })
+                        .insert(crate::gameplay::Brick { hits_left: *hits });
+                    if let Some(animation) = sheet.animation(*num) {
+                        brick.insert(animation);
+                    }
                 }
             }
             current_col += 1.0;
@@ -64,3 +175,77 @@ pub fn add_bricks(commands: &mut Commands, score: &mut crate::gameplay::Score, l
         current_row += 1.0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::CommandQueue;
+
+    const SHEET: &str = r#"(
+        texture: "texture/test.png",
+        texture_width: 100,
+        texture_height: 100,
+        sprites: [
+            SpriteSpecs(name: "brick", x: 0, y: 0, width: 44, height: 24, hitbox: Some(Rect(40.0, 20.0))),
+        ],
+    )"#;
+
+    const LEVEL: &str = r#"Level(
+        level_width: 400,
+        level_height: 300,
+        ball_velocity: 1.0,
+        block_margin: 10,
+        block_width: 80,
+        block_height: 40,
+        blocks: [Block(name: 'a', sprite: "brick"), Block(name: 'b', sprite: "missing")],
+        rows: ["a b", " a"],
+    )"#;
+
+    #[test]
+    fn bricks_collide_at_the_level_block_size() {
+        let (sheet, _, _) = SpriteSheet::from_ron(SHEET.as_bytes()).unwrap();
+        let level: Level = ron::de::from_str(LEVEL).unwrap();
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let mut score = crate::gameplay::Score::new();
+        add_bricks(
+            &mut Commands::new(&mut queue, &world),
+            &mut score,
+            &level,
+            &sheet,
+        );
+        queue.apply(&mut world);
+
+        // the brick without a sprite is left out
+        assert_eq!(score.bricks_left, 2);
+        let mut bricks: Vec<(Vec3, Vec2, Option<Vec2>)> = world
+            .query::<(&Transform, &crate::gameplay::Collider, &TextureAtlasSprite)>()
+            .iter(&world)
+            .map(|(transform, collider, sprite)| {
+                (
+                    transform.translation,
+                    collider.get_size(),
+                    sprite.custom_size,
+                )
+            })
+            .collect();
+        bricks.sort_by(|a, b| a.0.x.partial_cmp(&b.0.x).unwrap());
+        // the sprite is doubled so its 40 by 20 hitbox covers the 80 by 40 block
+        let stretched = Some(Vec2::new(88.0, 48.0));
+        assert_eq!(
+            bricks,
+            vec![
+                (
+                    Vec3::new(-160.0, 90.0, 0.0),
+                    Vec2::new(80.0, 40.0),
+                    stretched
+                ),
+                (
+                    Vec3::new(-70.0, 40.0, 0.0),
+                    Vec2::new(80.0, 40.0),
+                    stretched
+                ),
+            ]
+        );
+    }
+}
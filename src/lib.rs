@@ -0,0 +1,390 @@
+//! A block breaking game, packaged as a Bevy plugin.
+//!
+//! Add [`BlocksPlugin`] after `DefaultPlugins` to get the whole game; [`BlocksConfig`] picks
+//! the levels, sprite sheet, starting lives and the screen shown once loading has finished.
+//! [`CorePlugin`] on its own is the game logic without anything to see or hear, which is what
+//! [`headless`] runs. The individual modules are public so other binaries, tools and tests can
+//! reuse the game logic on its own.
+
+pub mod attract;
+pub mod autopilot;
+pub mod clock;
+pub mod controls;
+#[cfg(feature = "console")]
+pub mod console;
+#[cfg(feature = "debug-tools")]
+pub mod debug;
+pub mod error;
+pub mod events;
+pub mod feel;
+pub mod game_over;
+pub mod gameplay;
+pub mod headless;
+pub mod hud;
+pub mod level;
+pub mod loading;
+pub mod locale;
+pub mod main_menu;
+pub mod music;
+pub mod options_menu;
+pub mod particles;
+pub mod pause_menu;
+pub mod playfield;
+pub mod powerup;
+pub mod replay;
+pub mod replay_menu;
+pub mod settings;
+pub mod sound;
+pub mod sprite_sheet;
+pub mod state;
+pub mod theme;
+pub mod transition;
+
+use bevy::ecs::schedule::IntoSystemDescriptor;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::level::LevelSource;
+use crate::state::GameState;
+
+/// How the game is set up.
+#[derive(Debug, Clone)]
+pub struct BlocksConfig {
+    pub levels: LevelSource,
+    /// Sprite sheet for the ball, paddle and bricks, relative to the assets folder.
+    pub sprite_sheet: String,
+    /// Lives at the start of every new game.
+    pub starting_lives: i32,
+    /// Level the first game starts on, counting from 0.
+    pub start_level: usize,
+    /// Seed for gameplay randomness, such as power-up drops; a different one every game when
+    /// `None`.
+    pub seed: Option<u64>,
+    /// Screen shown once every asset has loaded.
+    pub initial_state: GameState,
+    /// Hands the paddle to an AI of this difficulty from the start.
+    pub autopilot: Option<autopilot::AiDifficulty>,
+}
+
+impl Default for BlocksConfig {
+    fn default() -> BlocksConfig {
+        BlocksConfig {
+            levels: LevelSource::Directory(PathBuf::from("assets/levels")),
+            sprite_sheet: sprite_sheet::SPRITE_SHEET_PATH.to_string(),
+            starting_lives: 3,
+            start_level: 0,
+            seed: None,
+            initial_state: GameState::MainMenu,
+            autopilot: None,
+        }
+    }
+}
+
+/// The stage of the app's schedule the game runs in, after `CoreStage::PreUpdate`.
+///
+/// It is a [`Schedule`] of its own, made of the stages in [`GameStage`], that runs once for every
+/// frame of the game. Playback runs it several times per update to fast-forward, see
+/// [`replay::repeat_frames`], while the rest of the app, such as the clocks and whatever does not
+/// follow the game state, runs once.
+#[derive(StageLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameSchedule;
+
+/// Stages of [`GameSchedule`].
+#[derive(StageLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameStage {
+    /// Reads the player's input, or plays it back.
+    PreUpdate,
+    /// Drives the `GameState` and runs every system set that follows it.
+    Update,
+    /// Records the frame that was just played.
+    Last,
+}
+
+/// Adds systems to the stages of [`GameSchedule`], which [`CorePlugin`] sets up.
+pub trait GameApp {
+    fn add_game_system<Params>(
+        &mut self,
+        stage: GameStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+
+    /// Adds a set to [`GameStage::Update`], where the `GameState` is driven.
+    fn add_game_system_set(&mut self, system_set: SystemSet) -> &mut Self;
+}
+
+impl GameApp for App {
+    fn add_game_system<Params>(
+        &mut self,
+        stage: GameStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.stage(GameSchedule, |schedule: &mut Schedule| {
+            schedule.add_system_to_stage(stage, system)
+        })
+    }
+
+    fn add_game_system_set(&mut self, system_set: SystemSet) -> &mut Self {
+        self.stage(GameSchedule, |schedule: &mut Schedule| {
+            schedule.add_system_set_to_stage(GameStage::Update, system_set)
+        })
+    }
+}
+
+/// The whole game: gameplay, menus, HUD, effects and audio.
+///
+/// Expects the resources and plugins of `DefaultPlugins`, and is made of [`CorePlugin`],
+/// [`AudioPlugin`] and [`PresentationPlugin`]. `Settings` and `Locale` are loaded from disk
+/// unless they were inserted beforehand, which `main` does so the window can be created from
+/// them.
+#[derive(Default)]
+pub struct BlocksPlugin {
+    pub config: BlocksConfig,
+}
+
+impl Plugin for BlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(CorePlugin {
+            config: self.config.clone(),
+        })
+        .add_plugin(AudioPlugin)
+        .add_plugin(PresentationPlugin);
+        #[cfg(feature = "debug-tools")]
+        app.add_plugin(debug::DebugPlugin);
+        #[cfg(feature = "console")]
+        app.add_plugin(console::ConsolePlugin);
+    }
+}
+
+/// Everything that decides how a game plays out: levels, gameplay, power-ups, input, the AI,
+/// replays, the attract demo and the flow between screens.
+///
+/// Needs input, window and asset resources, either from `DefaultPlugins` or stand-ins like the
+/// ones `headless::app` inserts, an `ActiveTheme` for the little it draws itself, such as the
+/// pause menu, and a `GameSpriteSheet` to build levels from. `Settings`, the `GameTime` clock,
+/// the `Recorder` and the state to start in are set up unless they were inserted beforehand;
+/// the state is inserted as a plain `State` resource, since it is driven in [`GameSchedule`].
+#[derive(Default)]
+pub struct CorePlugin {
+    pub config: BlocksConfig,
+}
+
+impl Plugin for CorePlugin {
+    fn build(&self, app: &mut App) {
+        let config = self.config.clone();
+        if !app.world.contains_resource::<settings::Settings>() {
+            app.insert_resource(settings::Settings::load());
+        }
+        if !app.world.contains_resource::<State<GameState>>() {
+            app.insert_resource(State::new(GameState::Loading));
+        }
+        if !app.world.contains_resource::<clock::GameTime>() {
+            app.insert_resource(clock::GameTime::quantized(clock::QUANTUM));
+        }
+        if !app.world.contains_resource::<replay::Recorder>() {
+            app.insert_resource(replay::Recorder::saving_to(replay::replay_dir()));
+        }
+        let (levels, mut level_errors) = config.levels.load();
+        if levels.is_empty() {
+            let error = format!("No levels found in {:?}", config.levels);
+            warn!("{}", error);
+            level_errors.push(error);
+        }
+        if config.start_level >= levels.len() && !levels.is_empty() {
+            warn!("There is no level {}", config.start_level + 1);
+        }
+        let playfield = levels
+            .get(config.start_level)
+            .or_else(|| levels.first())
+            .map(playfield::Playfield::from_level)
+            .unwrap_or_default();
+        let mut score = gameplay::Score::with_lives(config.starting_lives);
+        score.current_level = config.start_level;
+        let rng = config
+            .seed
+            .map(gameplay::GameRng::from_seed)
+            .unwrap_or_default();
+        let ai = autopilot::Autopilot::new(config.autopilot, config.seed);
+
+        events::add_events(app);
+        app.add_stage_after(
+            CoreStage::PreUpdate,
+            GameSchedule,
+            Schedule::default()
+                .with_run_criteria(IntoSystem::into_system(replay::repeat_frames))
+                .with_stage(GameStage::PreUpdate, SystemStage::parallel())
+                .with_stage(
+                    GameStage::Update,
+                    SystemStage::parallel().with_system_set(State::<GameState>::get_driver()),
+                )
+                .with_stage(GameStage::Last, SystemStage::parallel()),
+        );
+        app.insert_resource(score)
+            .insert_resource(levels)
+            .insert_resource(level::LevelErrors(level_errors))
+            .insert_resource(playfield)
+            .insert_resource(config)
+            .add_asset::<sprite_sheet::SpriteSheet>()
+            .init_asset_loader::<sprite_sheet::SpriteSheetLoader>()
+            .add_system_to_stage(
+                CoreStage::First,
+                clock::update_game_time_system.after(bevy::core::CoreSystem::Time),
+            )
+            .init_resource::<transition::Transitions>()
+            .add_game_system(
+                GameStage::Update,
+                transition::run_transitions_system.label(transition::TransitionSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                transition::block_input_system
+                    .after(bevy::input::InputSystem)
+                    .before(bevy::ui::UiSystem::Focus),
+            )
+            .init_resource::<controls::PlayerInput>()
+            .add_game_system(
+                GameStage::PreUpdate,
+                controls::read_player_input_system.label(controls::PlayerInputSystem),
+            )
+            .init_resource::<controls::ActiveController>()
+            .insert_resource(ai)
+            .add_game_system(
+                GameStage::PreUpdate,
+                controls::control_paddle_system
+                    .label(controls::PaddleControlSystem)
+                    .after(controls::PlayerInputSystem),
+            )
+            .add_system(autopilot::toggle_autopilot_system)
+            .add_system(autopilot::apply_autopilot_system)
+            .init_resource::<replay::Playback>()
+            .add_game_system(
+                GameStage::PreUpdate,
+                replay::play_frame_system
+                    .label(replay::PlaybackSystem)
+                    .after(controls::PaddleControlSystem),
+            )
+            .add_game_system(GameStage::Last, replay::record_frame_system)
+            .add_game_system(GameStage::Last, replay::finish_playback_system)
+            .add_system(replay::playback_controls_system)
+            .add_game_system_set(replay::enter_system_set())
+            .init_resource::<powerup::ActivePowerUps>()
+            .add_event::<powerup::PowerUpCollected>()
+            .insert_resource(rng)
+            .init_resource::<gameplay::GodMode>()
+            .init_resource::<error::GameError>()
+            .add_game_system_set(gameplay::enter_system_set())
+            .add_game_system_set(gameplay::update_system_set())
+            .add_game_system_set(gameplay::paused_update_system_set())
+            .add_game_system_set(gameplay::exit_system_set())
+            .add_game_system_set(powerup::update_system_set())
+            .add_game_system_set(powerup::exit_system_set())
+            .add_game_system_set(pause_menu::enter_system_set())
+            .add_game_system_set(pause_menu::update_system_set())
+            .add_game_system_set(pause_menu::exit_system_set())
+            .init_resource::<attract::Attract>()
+            .add_game_system(
+                GameStage::PreUpdate,
+                attract::demo_input_system.after(replay::PlaybackSystem),
+            )
+            .add_game_system_set(attract::menu_enter_system_set())
+            .add_game_system_set(attract::menu_update_system_set())
+            .add_game_system_set(attract::enter_system_set())
+            .add_game_system_set(attract::update_system_set())
+            .add_game_system_set(attract::game_over_system_set());
+    }
+}
+
+/// Sound effects and music.
+///
+/// Add after [`CorePlugin`], whose events and state it follows.
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<sound::PlaySound>()
+            .insert_resource(sound::SoundConfig::load())
+            .init_resource::<sound::SoundLibrary>()
+            .add_system(sound::gameplay_sounds_system)
+            .add_system(sound::progress_sounds_system)
+            .add_system(sound::menu_sounds_system)
+            .add_system(sound::play_sounds_system)
+            .insert_resource(music::MusicConfig::load())
+            .init_resource::<music::MusicController>()
+            .add_system(music::update_music_system)
+            .add_system(music::play_music_system);
+    }
+}
+
+/// What the player sees: cameras, themes and text, the loading and error screens, menus, the
+/// HUD, particles and the effects of [`feel`].
+///
+/// Add after [`CorePlugin`] and [`AudioPlugin`]; the loading screen also waits for the sounds
+/// to load.
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<locale::Locale>() {
+            let language = app.world.resource::<settings::Settings>().language.clone();
+            app.insert_resource(locale::Locale::load(&language));
+        }
+        let level_errors = app.world.resource::<level::LevelErrors>().0.clone();
+
+        app.insert_resource(ClearColor(theme::Theme::default().background))
+            .insert_resource(loading::LoadingAssets::new(level_errors))
+            .add_startup_system(setup)
+            .add_asset::<theme::Theme>()
+            .init_asset_loader::<theme::ThemeLoader>()
+            .init_resource::<theme::ActiveTheme>()
+            .init_resource::<sprite_sheet::GameSpriteSheet>()
+            .add_system(bevy::input::system::exit_on_esc_system)
+            .add_system(settings::apply_settings_system)
+            .add_system(locale::switch_language_system)
+            .add_system(locale::localize_text_system)
+            .add_system(theme::update_active_theme_system)
+            .add_system(theme::restyle_text_system)
+            .add_system(theme::restyle_backdrop_system)
+            .add_system(theme::restyle_button_system)
+            .add_system(playfield::fit_playfield_system)
+            .add_system(sprite_sheet::animate_sprites_system)
+            .add_system_to_stage(CoreStage::Last, transition::raise_overlay_system)
+            .add_system(autopilot::update_autopilot_hud_system)
+            .add_game_system_set(autopilot::enter_system_set())
+            .add_system(replay::update_replay_hud_system)
+            .init_resource::<hud::LevelClock>()
+            .insert_resource(particles::ParticleConfig::load())
+            .add_event::<particles::ParticleBurst>()
+            .add_plugin(feel::FeelPlugin)
+            .add_game_system_set(loading::enter_system_set())
+            .add_game_system_set(loading::update_system_set())
+            .add_game_system_set(loading::exit_system_set())
+            .add_game_system_set(loading::failed_enter_system_set())
+            .add_game_system_set(error::enter_system_set())
+            .add_game_system_set(error::update_system_set())
+            .add_game_system_set(error::exit_system_set())
+            .add_game_system_set(particles::update_system_set())
+            .add_game_system_set(particles::exit_system_set())
+            .add_game_system_set(hud::enter_system_set())
+            .add_game_system_set(hud::update_system_set())
+            .add_game_system_set(hud::exit_system_set())
+            .add_game_system_set(game_over::enter_system_set())
+            .add_game_system_set(game_over::update_system_set())
+            .add_game_system_set(game_over::exit_system_set())
+            .add_game_system_set(main_menu::enter_system_set())
+            .add_game_system_set(main_menu::update_system_set())
+            .add_game_system_set(main_menu::exit_system_set())
+            .add_game_system_set(options_menu::enter_system_set())
+            .add_game_system_set(options_menu::update_system_set())
+            .add_game_system_set(options_menu::exit_system_set())
+            .add_game_system_set(replay_menu::enter_system_set())
+            .add_game_system_set(replay_menu::update_system_set())
+            .add_game_system_set(replay_menu::exit_system_set());
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(playfield::MainCamera);
+    commands.spawn_bundle(UiCameraBundle::default());
+    playfield::spawn_letterbox(&mut commands);
+}
@@ -0,0 +1,262 @@
+use bevy::asset::{HandleId, LoadState};
+use bevy::prelude::*;
+
+use crate::level::Level;
+use crate::locale::LocalizedText;
+use crate::music::MusicConfig;
+use crate::sound::SoundLibrary;
+use crate::sprite_sheet::{GameSpriteSheet, SpriteSheet};
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedBackdrop, ThemedText};
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
+use crate::BlocksConfig;
+
+/// What the loading screen waits for besides the handles other resources already hold.
+pub struct LoadingAssets {
+    /// Every music track, kept loaded so switching between them never waits on the disk.
+    music: Vec<HandleUntyped>,
+    /// Everything that failed to load, including level files that could not be read.
+    pub errors: Vec<String>,
+}
+
+impl LoadingAssets {
+    /// Starts out with `errors` found before any asset was requested.
+    pub fn new(errors: Vec<String>) -> LoadingAssets {
+        LoadingAssets {
+            music: Vec::new(),
+            errors,
+        }
+    }
+}
+
+/// The filled part of the progress bar.
+#[derive(Component)]
+struct ProgressBar;
+
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Loading)
+        .with_system(load_music)
+        .with_system(setup_loading_screen)
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Loading).with_system(check_loading_system)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Loading).with_system(teardown)
+}
+
+pub fn failed_enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::LoadingFailed).with_system(setup_failed_screen)
+}
+
+fn load_music(
+    asset_server: Res<AssetServer>,
+    config: Res<MusicConfig>,
+    levels: Res<Vec<Level>>,
+    mut loading: ResMut<LoadingAssets>,
+) {
+    let mut tracks: Vec<&str> = vec![&config.menu, &config.game_over, &config.level];
+    tracks.extend(levels.iter().map(|level| level.music.as_str()));
+    tracks.retain(|track| !track.is_empty());
+    tracks.sort_unstable();
+    tracks.dedup();
+    loading.music = tracks
+        .into_iter()
+        .map(|track| asset_server.load_untyped(track))
+        .collect();
+}
+
+fn setup_loading_screen(mut commands: Commands, theme: Res<ActiveTheme>) {
+    let mut track_color = theme.theme.text;
+    track_color.set_a(0.25);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: UiColor(theme.theme.backdrop),
+            ..Default::default()
+        })
+        .insert(ThemedBackdrop)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        theme.text_style(ThemedText::Title),
+                        TextAlignment::default(),
+                    ),
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(LocalizedText::new("loading.title"))
+                .insert(ThemedText::Title);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.0), Val::Px(24.0)),
+                        padding: Rect::all(Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    color: UiColor(track_color),
+                    ..Default::default()
+                })
+                .with_children(|bar| {
+                    bar.spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                            ..Default::default()
+                        },
+                        color: UiColor(theme.theme.text),
+                        ..Default::default()
+                    })
+                    .insert(ProgressBar);
+                });
+        });
+}
+
+/// Fills the progress bar and moves on once every tracked asset has finished loading.
+///
+/// Goes to `BlocksConfig::initial_state` when everything loaded, or to the error screen once
+/// nothing is still loading and something failed.
+#[allow(clippy::too_many_arguments)]
+fn check_loading_system(
+    asset_server: Res<AssetServer>,
+    theme: Res<ActiveTheme>,
+    sheet: Res<GameSpriteSheet>,
+    sheets: Res<Assets<SpriteSheet>>,
+    atlases: Res<Assets<TextureAtlas>>,
+    sounds: Res<SoundLibrary>,
+    config: Res<BlocksConfig>,
+    mut loading: ResMut<LoadingAssets>,
+    mut transitions: ResMut<Transitions>,
+    mut bars: Query<&mut Style, With<ProgressBar>>,
+) {
+    if transitions.is_busy() {
+        return;
+    }
+    let mut handles: Vec<HandleId> = vec![theme.handle.id, theme.font.id, sheet.0.id];
+    // The sheet only names its texture once it has been read, and does not wait for it.
+    if let Some(atlas) = sheets
+        .get(&sheet.0)
+        .and_then(|sheet| atlases.get(&sheet.atlas))
+    {
+        handles.push(atlas.texture.id);
+    }
+    handles.extend(sounds.handles().map(|handle| handle.id));
+    handles.extend(loading.music.iter().map(|handle| handle.id));
+
+    let mut done = 0;
+    let mut failed = Vec::new();
+    for &id in &handles {
+        match asset_server.get_load_state(id) {
+            LoadState::Loaded => done += 1,
+            LoadState::Failed => {
+                done += 1;
+                failed.push(match asset_server.get_handle_path(id) {
+                    Some(path) => format!("Failed to load {}", path.path().display()),
+                    None => format!("Failed to load {:?}", id),
+                });
+            }
+            _ => (),
+        }
+    }
+    for mut style in bars.iter_mut() {
+        style.size.width = Val::Percent(100.0 * done as f32 / handles.len() as f32);
+    }
+    if done < handles.len() {
+        return;
+    }
+    if failed.is_empty() && loading.errors.is_empty() {
+        let next = match config.initial_state {
+            GameState::Loading | GameState::LoadingFailed => GameState::MainMenu,
+            ref state => state.clone(),
+        };
+        transitions.set(next, TransitionStyle::Fade);
+    } else {
+        loading.errors.extend(failed);
+        transitions.set(GameState::LoadingFailed, TransitionStyle::Fade);
+    }
+}
+
+fn setup_failed_screen(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    loading: Res<LoadingAssets>,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: UiColor(theme.theme.backdrop),
+            ..Default::default()
+        })
+        .insert(ThemedBackdrop)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        theme.text_style(ThemedText::Title),
+                        TextAlignment::default(),
+                    ),
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(LocalizedText::new("loading.failed"))
+                .insert(ThemedText::Title);
+            for error in &loading.errors {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            error.clone(),
+                            theme.text_style(ThemedText::Small),
+                            TextAlignment::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(ThemedText::Small);
+            }
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        theme.text_style(ThemedText::Body),
+                        TextAlignment::default(),
+                    ),
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(LocalizedText::new("loading.quit"))
+                .insert(ThemedText::Body);
+        });
+}
+
+fn teardown(
+    mut commands: Commands,
+    menu_nodes: Query<(Entity, &Node), Without<TransitionOverlay>>,
+) {
+    for (e, _n) in menu_nodes.iter() {
+        commands.entity(e).despawn();
+    }
+}
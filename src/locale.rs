@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::settings::Settings;
+
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// The string table for the current language, plus the English table used for missing keys.
+///
+/// Tables live in `assets/lang/<language>.ron` as a map from key to text. Text may contain
+/// `{name}` placeholders which are filled in by [`Locale::format`].
+pub struct Locale {
+    pub language: String,
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+fn load_table(language: &str) -> HashMap<String, String> {
+    let path = format!("assets/lang/{}.ron", language);
+    match std::fs::File::open(&path) {
+        Ok(file) => ron::de::from_reader(file).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", path, e);
+            HashMap::new()
+        }),
+        Err(e) => {
+            warn!("Failed to open {}: {}", path, e);
+            HashMap::new()
+        }
+    }
+}
+
+impl Locale {
+    pub fn load(language: &str) -> Locale {
+        let fallback = load_table(FALLBACK_LANGUAGE);
+        let strings = if language == FALLBACK_LANGUAGE {
+            fallback.clone()
+        } else {
+            load_table(language)
+        };
+        Locale {
+            language: language.to_string(),
+            strings,
+            fallback,
+        }
+    }
+
+    /// Looks up `key`, falling back to English and then to the key itself.
+    pub fn get(&self, key: &str) -> String {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Looks up `key` and replaces each `{name}` placeholder with its value from `args`.
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        let mut text = self.get(key);
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LocalizedArg {
+    /// Inserted as is.
+    Text(String),
+    /// Looked up in the string table, so it follows language changes too.
+    Key(String),
+}
+
+/// Keeps the first section of a `Text` in sync with a string-table entry.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct LocalizedText {
+    pub key: String,
+    pub args: Vec<(String, LocalizedArg)>,
+}
+
+impl LocalizedText {
+    pub fn new(key: &str) -> LocalizedText {
+        LocalizedText {
+            key: key.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn with_arg(mut self, name: &str, value: impl ToString) -> LocalizedText {
+        self.args
+            .push((name.to_string(), LocalizedArg::Text(value.to_string())));
+        self
+    }
+
+    pub fn with_key_arg(mut self, name: &str, key: &str) -> LocalizedText {
+        self.args
+            .push((name.to_string(), LocalizedArg::Key(key.to_string())));
+        self
+    }
+
+    pub fn render(&self, locale: &Locale) -> String {
+        let args: Vec<(&str, String)> = self
+            .args
+            .iter()
+            .map(|(name, arg)| {
+                let value = match arg {
+                    LocalizedArg::Text(text) => text.clone(),
+                    LocalizedArg::Key(key) => locale.get(key),
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+        locale.format(&self.key, &args)
+    }
+}
+
+/// Reloads the string table when the language setting changes.
+pub fn switch_language_system(
+    settings: Res<Settings>,
+    mut locale: ResMut<Locale>,
+    mut windows: ResMut<Windows>,
+) {
+    if settings.language == locale.language {
+        return;
+    }
+    *locale = Locale::load(&settings.language);
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_title(locale.get("window.title"));
+    }
+}
+
+/// Re-renders localized text whose key or arguments changed, or all of it when the language
+/// changes.
+pub fn localize_text_system(
+    locale: Res<Locale>,
+    mut text_query: Query<(ChangeTrackers<LocalizedText>, &LocalizedText, &mut Text)>,
+) {
+    for (tracker, localized, mut text) in text_query.iter_mut() {
+        if locale.is_changed() || tracker.is_changed() {
+            text.sections[0].value = localized.render(&locale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, text)| (key.to_string(), text.to_string()))
+            .collect()
+    }
+
+    fn locale() -> Locale {
+        Locale {
+            language: "fr".to_string(),
+            strings: table(&[("hud.score", "Score : {points}"), ("menu.play", "Jouer")]),
+            fallback: table(&[
+                ("hud.score", "Score: {points}"),
+                ("menu.play", "Play"),
+                ("menu.quit", "Quit"),
+            ]),
+        }
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_english_then_the_key() {
+        let locale = locale();
+        assert_eq!(locale.get("menu.play"), "Jouer");
+        assert_eq!(locale.get("menu.quit"), "Quit");
+        assert_eq!(locale.get("menu.missing"), "menu.missing");
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let locale = locale();
+        assert_eq!(
+            locale.format("hud.score", &[("points", "120".to_string())]),
+            "Score : 120"
+        );
+        // unknown placeholders are left alone, and unused arguments ignored
+        assert_eq!(
+            locale.format("hud.score", &[("lives", "3".to_string())]),
+            "Score : {points}"
+        );
+    }
+
+    #[test]
+    fn key_arguments_are_translated() {
+        let mut locale = locale();
+        locale
+            .strings
+            .insert("options.item".to_string(), "{name} ({value})".to_string());
+        let text = LocalizedText::new("options.item")
+            .with_key_arg("name", "menu.play")
+            .with_arg("value", 5);
+        assert_eq!(text.render(&locale), "Jouer (5)");
+    }
+
+    #[test]
+    fn every_language_has_the_english_keys() {
+        let english = load_table(FALLBACK_LANGUAGE);
+        assert!(!english.is_empty());
+        for language in crate::settings::LANGUAGES {
+            let table = load_table(language);
+            let mut missing: Vec<&String> = english
+                .keys()
+                .filter(|key| !table.contains_key(*key))
+                .collect();
+            missing.sort();
+            assert!(missing.is_empty(), "{} is missing {:?}", language, missing);
+        }
+    }
+}
@@ -1,67 +1,206 @@
-mod gameplay;
-mod level;
-mod pause_menu;
-mod main_menu;
-mod game_over;
-mod sprite_sheet;
-mod state;
+//! The game.
+//!
+//! ```text
+//! bevy_blocks [--level N|PATH] [--levels-dir DIR] [--seed N] [--lives N] [--windowed WxH]
+//!             [--no-diagnostics] [--autopilot easy|normal|hard] [--headless --ticks N]
+//!             [--verify-replay PATH]
+//! ```
+//!
+//! `--level` starts straight in the given level, by number or from a level file. With
+//! `--headless` the game runs without a window for `--ticks` frames, or until the game ends,
+//! and prints the final score as JSON. `--autopilot` hands the paddle to an AI, with or without
+//! a window; F6 switches it off, to assisting the player or back on in the windowed game.
+//! `--verify-replay` plays a replay file back without a window, prints the final score the same
+//! way and fails if it differs from the recorded one.
+
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
 };
+use bevy_blocks::error::GameError;
+use bevy_blocks::gameplay::Score;
+use bevy_blocks::level::LevelSource;
+use bevy_blocks::replay::{self, FinalScore, Replay};
+use bevy_blocks::settings::DisplayMode;
+use bevy_blocks::state::GameState;
+use bevy_blocks::transition::{TransitionStyle, Transitions};
+use bevy_blocks::{locale::Locale, settings::Settings, BlocksConfig, BlocksPlugin};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: bevy_blocks [--level N|PATH] [--levels-dir DIR] [--seed N] \
+                     [--lives N] [--windowed WxH] [--no-diagnostics] \
+                     [--autopilot easy|normal|hard] [--headless --ticks N] [--verify-replay PATH]";
 
-const SCREEN_WIDTH: f32 = 1100.;
-const SCREEN_HEIGHT: f32 = 720.;
-const BACKGROUND_COLOR: Color = Color::rgb(0.58, 0.31, 0.15);
+struct Options {
+    config: BlocksConfig,
+    /// Window size for this run, overriding the display settings.
+    windowed: Option<(u32, u32)>,
+    diagnostics: bool,
+    /// Frames to simulate without a window.
+    headless_ticks: Option<u32>,
+    /// Replay to play back without a window.
+    verify_replay: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        config: BlocksConfig::default(),
+        windowed: None,
+        diagnostics: true,
+        headless_ticks: None,
+        verify_replay: None,
+    };
+    let mut level = None;
+    let mut levels_dir = None;
+    let mut headless = false;
+    let mut ticks = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--level" => level = Some(value()?),
+            "--levels-dir" => levels_dir = Some(PathBuf::from(value()?)),
+            "--seed" => options.config.seed = Some(value()?.parse()?),
+            "--lives" => options.config.starting_lives = value()?.parse()?,
+            "--windowed" => {
+                let size = value()?;
+                let (width, height) = size
+                    .split_once('x')
+                    .ok_or(format!("expected WIDTHxHEIGHT, got {}", size))?;
+                options.windowed = Some((width.parse()?, height.parse()?));
+            }
+            "--no-diagnostics" => options.diagnostics = false,
+            "--autopilot" => options.config.autopilot = Some(value()?.parse()?),
+            "--headless" => headless = true,
+            "--ticks" => ticks = Some(value()?.parse()?),
+            "--verify-replay" => options.verify_replay = Some(PathBuf::from(value()?)),
+            "-h" | "--help" => return Err(USAGE.into()),
+            _ => return Err(format!("unknown option {}\n{}", arg, USAGE).into()),
+        }
+    }
+    if let Some(dir) = levels_dir {
+        options.config.levels = LevelSource::Directory(dir);
+    }
+    if let Some(level) = level {
+        match level.parse::<usize>() {
+            Ok(0) => return Err("levels are numbered from 1".into()),
+            Ok(number) => options.config.start_level = number - 1,
+            Err(_) => options.config.levels = LevelSource::Files(vec![PathBuf::from(level)]),
+        }
+        options.config.initial_state = GameState::Level;
+    }
+    options.headless_ticks = match (headless, ticks) {
+        (true, Some(ticks)) => Some(ticks),
+        (true, None) => return Err("--headless needs --ticks N".into()),
+        (false, Some(_)) => return Err("--ticks only applies with --headless".into()),
+        (false, None) => None,
+    };
+    if options.verify_replay.is_some() && options.headless_ticks.is_some() {
+        return Err("--verify-replay cannot be combined with --headless".into());
+    }
+    Ok(options)
+}
 
 fn main() {
-    App::new()
-        .add_state(state::GameState::MainMenu)
-        .insert_resource(ClearColor(BACKGROUND_COLOR))
-        .insert_resource(WindowDescriptor {
-            title: "Break the Blocks!".to_string(),
-            width: SCREEN_WIDTH,
-            height: SCREEN_HEIGHT,
-            present_mode: bevy::window::PresentMode::Mailbox,
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(ticks) = options.headless_ticks {
+        if let Err(e) = run_headless(options.config, ticks) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(path) = &options.verify_replay {
+        match verify_replay(path, options.config) {
+            Ok(true) => (),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{:#}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // the window is created by `DefaultPlugins`, so its settings have to be known up front
+    let mut settings = Settings::load();
+    if let Some(resolution) = options.windowed {
+        settings.display_mode = DisplayMode::Windowed;
+        settings.resolution = resolution;
+    }
+    let locale = Locale::load(&settings.language);
+    let mut app = App::new();
+    app.insert_resource(settings.window_descriptor(locale.get("window.title")))
+        .insert_resource(settings)
+        .insert_resource(locale)
+        .insert_resource(bevy::asset::AssetServerSettings {
+            watch_for_changes: true,
             ..Default::default()
         })
-        .add_startup_system(setup)
         .add_plugins(DefaultPlugins)
-        .add_system(bevy::input::system::exit_on_esc_system.system())
-        .add_system_set(gameplay::enter_system_set())
-        .add_system_set(gameplay::update_system_set())
-        .add_system_set(gameplay::paused_update_system_set())
-        .add_system_set(gameplay::exit_system_set())
-        .add_system_set(pause_menu::enter_system_set())
-        .add_system_set(pause_menu::update_system_set())
-        .add_system_set(pause_menu::exit_system_set())
-        .add_system_set(game_over::enter_system_set())
-        .add_system_set(game_over::update_system_set())
-        .add_system_set(game_over::exit_system_set())
-        .add_system_set(main_menu::enter_system_set())
-        .add_system_set(main_menu::update_system_set())
-        .add_system_set(main_menu::exit_system_set())
-        .add_plugin(LogDiagnosticsPlugin::default())
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .run();
+        .add_plugin(BlocksPlugin {
+            config: options.config,
+        });
+    if options.diagnostics {
+        app.add_plugin(LogDiagnosticsPlugin::default())
+            .add_plugin(FrameTimeDiagnosticsPlugin);
+    }
+    app.run();
 }
-pub fn load_all_levels() -> std::vec::Vec<level::Level> {
-    let levels_dir = std::fs::read_dir("assets/levels").unwrap();
-    levels_dir
-        .filter_map(Result::ok)
-        .map(|de| ron::de::from_reader(std::fs::File::open(de.path()).unwrap()))
-        .filter_map(Result::ok)
-        .collect()
+
+/// Plays `ticks` frames without a window and prints the final score.
+fn run_headless(config: BlocksConfig, ticks: u32) -> anyhow::Result<()> {
+    let mut app = bevy_blocks::headless::app(config)?;
+    for _ in 0..ticks {
+        match app.world.resource::<State<GameState>>().current() {
+            GameState::Level => (),
+            // nobody is there to dismiss the pause menu
+            GameState::PauseMenu => app
+                .world
+                .resource_mut::<Transitions>()
+                .pop(TransitionStyle::Cut),
+            // out of lives or levels, or something went wrong
+            _ => break,
+        }
+        app.update();
+    }
+    if let Some(message) = &app.world.resource::<GameError>().message {
+        anyhow::bail!("{}", message);
+    }
+    println!(
+        "{}",
+        score_json(&FinalScore::from(app.world.resource::<Score>()))
+    );
+    Ok(())
 }
-fn setup(
-    mut commands: Commands,
-    mut asset_server: Res<AssetServer>,
-    atlases: ResMut<Assets<TextureAtlas>>,
-) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-    commands.spawn_bundle(UiCameraBundle::default());
-    let level_files = load_all_levels();
-    commands.insert_resource(level_files.clone());
-    let _level = level_files[0].clone();
-    let _atlas = sprite_sheet::build_sprite_sheet(&mut asset_server, atlases);
+
+/// Plays the replay at `path` without a window and prints its final score. Returns whether
+/// the score matches the recorded one.
+fn verify_replay(path: &Path, config: BlocksConfig) -> anyhow::Result<bool> {
+    let replay = Replay::load(path)?;
+    let result = replay::play_headless(&replay, config)?;
+    println!("{}", score_json(&result));
+    if result != replay.result {
+        eprintln!("expected {}", score_json(&replay.result));
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn score_json(score: &FinalScore) -> String {
+    format!(
+        "{{\"level\":{},\"points\":{},\"lives\":{},\"bricks_left\":{},\"combo\":{}}}",
+        score.level + 1,
+        score.points,
+        score.lives,
+        score.bricks_left,
+        score.combo
+    )
 }
@@ -1,9 +1,21 @@
 use bevy::prelude::*;
 use bevy::app::AppExit;
+use crate::locale::LocalizedText;
+use crate::sprite_sheet::{GameSpriteSheet, SpriteSheet};
 use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedBackdrop, ThemedText};
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
+use crate::BlocksConfig;
 
-#[derive(Component)]
-struct PauseUI;
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    Quit,
+    Replays,
+    Options,
+    Start,
+}
+
+type ChangedButton = (Changed<Interaction>, With<Button>);
 
 pub fn enter_system_set() -> SystemSet {
     SystemSet::on_enter(crate::state::GameState::MainMenu).with_system(setup_main_menu)
@@ -16,12 +28,13 @@ pub fn update_system_set() -> SystemSet {
 pub fn exit_system_set() -> SystemSet {
     SystemSet::on_exit(GameState::MainMenu).with_system(teardown)
 }
-pub fn setup_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let text_style = TextStyle {
-        font: asset_server.load("font/FiraSans-Light.ttf"),
-        font_size: 40.0,
-        color: Color::rgb(0.71, 0.8, 0.4),
-    };
+pub fn setup_main_menu(mut commands: Commands, theme: Res<ActiveTheme>) {
+    spawn_main_menu(&mut commands, &theme);
+}
+
+/// Spawns the menu's buttons on a full-screen backdrop, and returns the backdrop.
+pub fn spawn_main_menu(commands: &mut Commands, theme: &ActiveTheme) -> Entity {
+    let text_style = theme.text_style(ThemedText::Body);
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -31,14 +44,10 @@ pub fn setup_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                 justify_content: JustifyContent::SpaceAround,
                 ..Default::default()
             },
-            color: UiColor(Color::Rgba {
-                red: 0.1,
-                green: 0.1,
-                blue: 0.1,
-                alpha: 1.0,
-            }),
+            color: UiColor(theme.theme.backdrop),
             ..Default::default()
         })
+        .insert(ThemedBackdrop)
         .with_children(|grandparent| {
             grandparent
                 .spawn_bundle(NodeBundle {
@@ -52,78 +61,75 @@ pub fn setup_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ..Default::default()
                 })
                 .with_children(|parent| {
-                    parent
-                        .spawn_bundle(ButtonBundle::default())
-                        .with_children(|btn| {
-                            btn.spawn_bundle(TextBundle {
-                                text: Text::with_section(
-                                    "Quit",
-                                    text_style.clone(),
-                                    TextAlignment {
-                                        vertical: VerticalAlign::Center,
-                                        horizontal: HorizontalAlign::Center,
+                    for (button, key) in [
+                        (MenuButton::Quit, "main_menu.quit"),
+                        (MenuButton::Replays, "main_menu.replays"),
+                        (MenuButton::Options, "main_menu.options"),
+                        (MenuButton::Start, "main_menu.start"),
+                    ] {
+                        parent
+                            .spawn_bundle(ButtonBundle::default())
+                            .insert(button)
+                            .with_children(|btn| {
+                                btn.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        "",
+                                        text_style.clone(),
+                                        TextAlignment {
+                                            vertical: VerticalAlign::Center,
+                                            horizontal: HorizontalAlign::Center,
+                                        },
+                                    ),
+                                    style: Style {
+                                        flex_grow: 0.0,
+                                        ..Default::default()
                                     },
-                                ),
-                                style: Style {
-                                    flex_grow: 0.0,
                                     ..Default::default()
-                                },
-                                ..Default::default()
+                                })
+                                .insert(LocalizedText::new(key))
+                                .insert(ThemedText::Body);
                             });
-                        });
-
-                    parent
-                        .spawn_bundle(ButtonBundle::default())
-                        .with_children(|btn| {
-                            btn.spawn_bundle(TextBundle {
-                                text: Text::with_section(
-                                    "Start",
-                                    text_style.clone(),
-                                    TextAlignment {
-                                        vertical: VerticalAlign::Center,
-                                        horizontal: HorizontalAlign::Center,
-                                    },
-                                ),
-                                style: Style {
-                                    flex_grow: 0.0,
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            });
-                        });
+                    }
                 });
-        });
+        })
+        .id()
 }
 
 fn menu_update(
     mut commands: Commands,
-    mut state: ResMut<State<crate::state::GameState>>,
-    mut interaction_query: Query<
-        (&Interaction, &Children),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut text_query: Query<&mut Text>,
+    mut transitions: ResMut<Transitions>,
+    sprite_sheet: Res<GameSpriteSheet>,
+    sheets: Res<Assets<SpriteSheet>>,
+    config: Res<BlocksConfig>,
+    interaction_query: Query<(&Interaction, &MenuButton), ChangedButton>,
     mut exit: EventWriter<AppExit>,
 ) {
-    for (interaction, children) in interaction_query.iter_mut() {
-        let text = text_query.get_mut(children[0]).unwrap();
-        match *interaction {
-            Interaction::Clicked => {
-                if text.sections[0].value == "Quit" {
-                    exit.send(AppExit);
-                }
-                else if text.sections[0].value == "Start" {
-                        state.set(GameState::Level).expect("Failed to start level");
-                        commands.insert_resource(crate::gameplay::Score::new());
-                }
-                else { () }},
-
-            _ => (),
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            MenuButton::Quit => exit.send(AppExit),
+            // the level is built from the sprite sheet, so wait for it to load
+            MenuButton::Start if sheets.contains(&sprite_sheet.0) => {
+                transitions.set(GameState::Level, TransitionStyle::Wipe);
+                commands.insert_resource(crate::gameplay::Score::with_lives(config.starting_lives));
+            }
+            MenuButton::Start => (),
+            MenuButton::Options => {
+                transitions.set(GameState::Options, TransitionStyle::Fade);
+            }
+            MenuButton::Replays => {
+                transitions.set(GameState::Replays, TransitionStyle::Fade);
+            }
         }
     }
 }
 
-fn teardown(mut commands: Commands, menu_nodes: Query<(Entity, &Node)>) {
+fn teardown(
+    mut commands: Commands,
+    menu_nodes: Query<(Entity, &Node), Without<TransitionOverlay>>,
+) {
     for (e, _n) in menu_nodes.iter() {
         commands.entity(e).despawn();
     }
@@ -0,0 +1,340 @@
+use bevy::audio::{Audio, AudioSink, PlaybackSettings};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::gameplay::Score;
+use crate::level::Level;
+use crate::settings::Settings;
+use crate::state::GameState;
+
+/// Tracks and mixing settings, read from `assets/audio/music.ron`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MusicConfig {
+    pub menu: String,
+    pub game_over: String,
+    /// Played in levels that do not name their own track.
+    pub level: String,
+    pub crossfade_seconds: f32,
+    /// Volume of the music while the game is paused, relative to normal.
+    pub pause_volume: f32,
+}
+
+impl MusicConfig {
+    pub fn load() -> MusicConfig {
+        let path = "assets/audio/music.ron";
+        match std::fs::File::open(path) {
+            Ok(file) => ron::de::from_reader(file).unwrap_or_else(|e| {
+                warn!("Failed to parse {}: {}", path, e);
+                MusicConfig::default()
+            }),
+            Err(e) => {
+                warn!("Failed to open {}: {}", path, e);
+                MusicConfig::default()
+            }
+        }
+    }
+
+    /// The track that belongs to `state`, given the level being played.
+    pub fn track_for<'a>(&'a self, state: &GameState, level: Option<&'a Level>) -> &'a str {
+        match state {
+            GameState::Loading | GameState::LoadingFailed | GameState::Error => "",
+            GameState::MainMenu | GameState::Options | GameState::Replays => &self.menu,
+            GameState::GameOver => &self.game_over,
+            GameState::Level | GameState::PauseMenu => match level {
+                Some(level) if !level.music.is_empty() => &level.music,
+                _ => &self.level,
+            },
+        }
+    }
+}
+
+impl Default for MusicConfig {
+    fn default() -> MusicConfig {
+        MusicConfig {
+            menu: String::new(),
+            game_over: String::new(),
+            level: String::new(),
+            crossfade_seconds: 1.5,
+            pause_volume: 0.35,
+        }
+    }
+}
+
+struct Channel {
+    track: String,
+    /// Fade level between 0 and 1.
+    level: f32,
+}
+
+/// Decides which tracks should be audible and how loud, without touching any audio.
+///
+/// Switching tracks fades the old one out while the new one fades in. A track that is asked for
+/// again while still fading out fades back in from where it was rather than restarting.
+pub struct MusicController {
+    channels: Vec<Channel>,
+    current: Option<String>,
+    fade_seconds: f32,
+    duck_level: f32,
+    ducked: bool,
+    duck: f32,
+}
+
+impl MusicController {
+    pub fn new(fade_seconds: f32, duck_level: f32) -> MusicController {
+        MusicController {
+            channels: Vec::new(),
+            current: None,
+            fade_seconds,
+            duck_level,
+            ducked: false,
+            duck: 1.0,
+        }
+    }
+
+    /// Makes `track` the one that should be playing; an empty name fades everything out.
+    pub fn play(&mut self, track: &str) {
+        if track.is_empty() {
+            self.current = None;
+            return;
+        }
+        if self.current.as_deref() == Some(track) {
+            return;
+        }
+        self.current = Some(track.to_string());
+        if !self.channels.iter().any(|channel| channel.track == track) {
+            self.channels.push(Channel {
+                track: track.to_string(),
+                level: 0.0,
+            });
+        }
+    }
+
+    /// Lowers the volume of everything to the duck level, e.g. while paused.
+    pub fn set_ducked(&mut self, ducked: bool) {
+        self.ducked = ducked;
+    }
+
+    /// Advances fades by `seconds`, dropping tracks that have faded out completely.
+    pub fn tick(&mut self, seconds: f32) {
+        let step = if self.fade_seconds > 0.0 {
+            seconds / self.fade_seconds
+        } else {
+            1.0
+        };
+        let current = self.current.clone();
+        for channel in self.channels.iter_mut() {
+            let target = if current.as_ref() == Some(&channel.track) {
+                1.0
+            } else {
+                0.0
+            };
+            channel.level = approach(channel.level, target, step);
+        }
+        self.channels
+            .retain(|channel| channel.level > 0.0 || current.as_ref() == Some(&channel.track));
+        let duck_target = if self.ducked { self.duck_level } else { 1.0 };
+        self.duck = approach(self.duck, duck_target, step);
+    }
+
+    /// Every track that should be playing, with its volume between 0 and 1.
+    pub fn channels(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.channels
+            .iter()
+            .map(move |channel| (channel.track.as_str(), channel.level * self.duck))
+    }
+}
+
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+impl FromWorld for MusicController {
+    fn from_world(world: &mut World) -> MusicController {
+        let config = world.resource::<MusicConfig>();
+        MusicController::new(config.crossfade_seconds, config.pause_volume)
+    }
+}
+
+/// Picks the track for the current `GameState` and advances the crossfade.
+pub fn update_music_system(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    config: Res<MusicConfig>,
+    levels: Res<Vec<Level>>,
+    score: Option<Res<Score>>,
+    attract: Res<crate::attract::Attract>,
+    mut controller: ResMut<MusicController>,
+) {
+    let level = score.and_then(|score| levels.get(score.current_level));
+    // the demo plays behind the main menu, so it keeps the menu's music
+    let state = match state.current() {
+        _ if attract.is_playing() => &GameState::MainMenu,
+        state => state,
+    };
+    let track = config.track_for(state, level).to_string();
+    controller.play(&track);
+    controller.set_ducked(*state == GameState::PauseMenu);
+    controller.tick(time.delta_seconds());
+}
+
+/// Starts, stops and sets the volume of looping music so it matches the controller.
+pub fn play_music_system(
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    settings: Res<Settings>,
+    controller: Res<MusicController>,
+    mut sinks: Local<HashMap<String, Handle<AudioSink>>>,
+) {
+    let volume = settings.master_volume * settings.music_volume;
+    for (track, level) in controller.channels() {
+        if !sinks.contains_key(track) {
+            let sink = audio.play_with_settings(
+                asset_server.load(track),
+                PlaybackSettings::LOOP.with_volume(0.0),
+            );
+            sinks.insert(track.to_string(), audio_sinks.get_handle(sink));
+        }
+        if let Some(sink) = audio_sinks.get(&sinks[track]) {
+            sink.set_volume(level * volume);
+        }
+    }
+    sinks.retain(|track, handle| {
+        let playing = controller.channels().any(|(name, _)| name == track);
+        if !playing {
+            if let Some(sink) = audio_sinks.get(&*handle) {
+                sink.stop();
+            }
+        }
+        playing
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(music: &MusicController, track: &str) -> f32 {
+        music
+            .channels()
+            .find(|(name, _)| *name == track)
+            .map_or(0.0, |(_, volume)| volume)
+    }
+
+    #[test]
+    fn first_track_fades_in() {
+        let mut music = MusicController::new(1.0, 0.5);
+        music.play("menu");
+        assert_eq!(volume(&music, "menu"), 0.0);
+        music.tick(0.5);
+        assert_eq!(volume(&music, "menu"), 0.5);
+        music.tick(1.0);
+        assert_eq!(volume(&music, "menu"), 1.0);
+    }
+
+    #[test]
+    fn switching_tracks_crossfades() {
+        let mut music = MusicController::new(1.0, 0.5);
+        music.play("menu");
+        music.tick(1.0);
+        music.play("level");
+        music.tick(0.25);
+        assert_eq!(volume(&music, "menu"), 0.75);
+        assert_eq!(volume(&music, "level"), 0.25);
+        music.tick(1.0);
+        assert_eq!(volume(&music, "level"), 1.0);
+        assert_eq!(music.channels().count(), 1);
+        assert_eq!(music.current.as_deref(), Some("level"));
+    }
+
+    #[test]
+    fn playing_the_same_track_again_changes_nothing() {
+        let mut music = MusicController::new(1.0, 0.5);
+        music.play("menu");
+        music.tick(1.0);
+        music.play("menu");
+        music.tick(0.1);
+        assert_eq!(volume(&music, "menu"), 1.0);
+        assert_eq!(music.channels().count(), 1);
+    }
+
+    #[test]
+    fn returning_to_a_fading_track_fades_it_back_in() {
+        let mut music = MusicController::new(1.0, 0.5);
+        music.play("menu");
+        music.tick(1.0);
+        music.play("level");
+        music.tick(0.5);
+        music.play("menu");
+        music.tick(0.25);
+        assert_eq!(volume(&music, "menu"), 0.75);
+        assert_eq!(volume(&music, "level"), 0.25);
+        assert_eq!(music.channels().count(), 2);
+    }
+
+    #[test]
+    fn ducking_lowers_and_restores_the_volume() {
+        let mut music = MusicController::new(1.0, 0.4);
+        music.play("level");
+        music.tick(1.0);
+        music.set_ducked(true);
+        music.tick(1.0);
+        assert!((volume(&music, "level") - 0.4).abs() < 1e-6);
+        music.set_ducked(false);
+        music.tick(1.0);
+        assert_eq!(volume(&music, "level"), 1.0);
+    }
+
+    #[test]
+    fn empty_track_fades_everything_out() {
+        let mut music = MusicController::new(1.0, 0.5);
+        music.play("menu");
+        music.tick(1.0);
+        music.play("");
+        music.tick(1.0);
+        assert_eq!(music.current.as_deref(), None);
+        assert_eq!(music.channels().count(), 0);
+    }
+
+    #[test]
+    fn zero_fade_time_switches_at_once() {
+        let mut music = MusicController::new(0.0, 0.5);
+        music.play("menu");
+        music.tick(0.0);
+        assert_eq!(volume(&music, "menu"), 1.0);
+        music.play("level");
+        music.tick(0.0);
+        assert_eq!(volume(&music, "level"), 1.0);
+        assert_eq!(volume(&music, "menu"), 0.0);
+    }
+
+    #[test]
+    fn states_pick_their_tracks() {
+        let config = MusicConfig {
+            menu: "menu".to_string(),
+            game_over: "over".to_string(),
+            level: "default".to_string(),
+            ..MusicConfig::default()
+        };
+        let mut level: Level = ron::de::from_str(
+            "Level(level_width: 100, level_height: 100, ball_velocity: 1.0, block_margin: 0, \
+             block_width: 10, block_height: 10, blocks: [], rows: [])",
+        )
+        .unwrap();
+        assert_eq!(config.track_for(&GameState::MainMenu, None), "menu");
+        assert_eq!(config.track_for(&GameState::Options, None), "menu");
+        assert_eq!(config.track_for(&GameState::GameOver, None), "over");
+        assert_eq!(config.track_for(&GameState::Level, Some(&level)), "default");
+        level.music = "level".to_string();
+        assert_eq!(config.track_for(&GameState::Level, Some(&level)), "level");
+        assert_eq!(
+            config.track_for(&GameState::PauseMenu, Some(&level)),
+            "level"
+        );
+    }
+}
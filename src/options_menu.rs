@@ -0,0 +1,303 @@
+use crate::locale::LocalizedText;
+use crate::settings::{ControlScheme, DisplayMode, Settings, VsyncMode, LANGUAGES, RESOLUTIONS};
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedBackdrop, ThemedText};
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy, Debug)]
+enum OptionButton {
+    DisplayMode,
+    Resolution,
+    Vsync,
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    ControlScheme,
+    ScreenShake,
+    HitStop,
+    Squash,
+    Flash,
+    Particles,
+    Language,
+    Theme,
+    TransitionDuration,
+    Back,
+}
+
+type ChangedButton = (Changed<Interaction>, With<Button>);
+
+const OPTION_BUTTONS: [OptionButton; 16] = [
+    OptionButton::DisplayMode,
+    OptionButton::Resolution,
+    OptionButton::Vsync,
+    OptionButton::MasterVolume,
+    OptionButton::SfxVolume,
+    OptionButton::MusicVolume,
+    OptionButton::ControlScheme,
+    OptionButton::ScreenShake,
+    OptionButton::HitStop,
+    OptionButton::Squash,
+    OptionButton::Flash,
+    OptionButton::Particles,
+    OptionButton::Language,
+    OptionButton::Theme,
+    OptionButton::TransitionDuration,
+    OptionButton::Back,
+];
+
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Options).with_system(setup_options_menu)
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Options).with_system(menu_update)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Options)
+        .with_system(teardown)
+        .with_system(save_settings)
+}
+
+fn option_label(button: OptionButton, settings: &Settings) -> LocalizedText {
+    let percent = |value: f32| format!("{:.0}", value * 100.0);
+    match button {
+        OptionButton::DisplayMode => LocalizedText::new("options.display").with_key_arg(
+            "value",
+            match settings.display_mode {
+                DisplayMode::Windowed => "value.windowed",
+                DisplayMode::BorderlessFullscreen => "value.borderless_fullscreen",
+                DisplayMode::Fullscreen => "value.fullscreen",
+            },
+        ),
+        OptionButton::Resolution => LocalizedText::new("options.resolution")
+            .with_arg("width", settings.resolution.0)
+            .with_arg("height", settings.resolution.1),
+        OptionButton::Vsync => LocalizedText::new("options.vsync").with_key_arg(
+            "value",
+            match settings.vsync {
+                VsyncMode::Off => "value.vsync_off",
+                VsyncMode::On => "value.vsync_on",
+                VsyncMode::Mailbox => "value.mailbox",
+            },
+        ),
+        OptionButton::MasterVolume => LocalizedText::new("options.master_volume")
+            .with_arg("value", percent(settings.master_volume)),
+        OptionButton::SfxVolume => {
+            LocalizedText::new("options.sfx_volume").with_arg("value", percent(settings.sfx_volume))
+        }
+        OptionButton::MusicVolume => LocalizedText::new("options.music_volume")
+            .with_arg("value", percent(settings.music_volume)),
+        OptionButton::ControlScheme => LocalizedText::new("options.controls").with_key_arg(
+            "value",
+            match settings.control_scheme {
+                ControlScheme::ArrowKeys => "value.arrow_keys",
+                ControlScheme::Wasd => "value.wasd",
+                ControlScheme::Mouse => "value.mouse",
+            },
+        ),
+        OptionButton::ScreenShake => LocalizedText::new("options.screen_shake")
+            .with_arg("value", percent(settings.screen_shake)),
+        OptionButton::HitStop => {
+            LocalizedText::new("options.hit_stop").with_arg("value", percent(settings.hit_stop))
+        }
+        OptionButton::Squash => {
+            LocalizedText::new("options.squash").with_arg("value", percent(settings.squash))
+        }
+        OptionButton::Flash => {
+            LocalizedText::new("options.flash").with_arg("value", percent(settings.flash))
+        }
+        OptionButton::Particles => LocalizedText::new("options.particles").with_key_arg(
+            "value",
+            if settings.particles {
+                "value.on"
+            } else {
+                "value.off"
+            },
+        ),
+        // each table names its own language, so this follows the language switch
+        OptionButton::Language => {
+            LocalizedText::new("options.language").with_key_arg("value", "language.name")
+        }
+        OptionButton::Theme => {
+            LocalizedText::new("options.theme").with_arg("value", &settings.theme)
+        }
+        OptionButton::TransitionDuration => LocalizedText::new("options.transitions")
+            .with_arg("value", settings.transition_duration),
+        OptionButton::Back => LocalizedText::new("menu.back"),
+    }
+}
+
+/// Returns the entry after `current` in `values`, wrapping around at the end.
+fn next_in<T: PartialEq + Clone>(values: &[T], current: &T) -> T {
+    let idx = values.iter().position(|v| v == current).unwrap_or(0);
+    values[(idx + 1) % values.len()].clone()
+}
+
+/// Steps a volume-like value up by 10%, wrapping back to zero after 100%.
+fn next_level(value: f32) -> f32 {
+    let next = (value * 10.0).round() + 1.0;
+    if next > 10.0 {
+        0.0
+    } else {
+        next / 10.0
+    }
+}
+
+fn cycle_option(button: OptionButton, settings: &mut Settings) {
+    match button {
+        OptionButton::DisplayMode => {
+            settings.display_mode = next_in(
+                &[
+                    DisplayMode::Windowed,
+                    DisplayMode::BorderlessFullscreen,
+                    DisplayMode::Fullscreen,
+                ],
+                &settings.display_mode,
+            )
+        }
+        OptionButton::Resolution => {
+            settings.resolution = next_in(RESOLUTIONS, &settings.resolution)
+        }
+        OptionButton::Vsync => {
+            settings.vsync = next_in(
+                &[VsyncMode::Off, VsyncMode::On, VsyncMode::Mailbox],
+                &settings.vsync,
+            )
+        }
+        OptionButton::MasterVolume => settings.master_volume = next_level(settings.master_volume),
+        OptionButton::SfxVolume => settings.sfx_volume = next_level(settings.sfx_volume),
+        OptionButton::MusicVolume => settings.music_volume = next_level(settings.music_volume),
+        OptionButton::ControlScheme => {
+            settings.control_scheme = next_in(
+                &[
+                    ControlScheme::ArrowKeys,
+                    ControlScheme::Wasd,
+                    ControlScheme::Mouse,
+                ],
+                &settings.control_scheme,
+            )
+        }
+        OptionButton::ScreenShake => {
+            settings.screen_shake = next_in(&[0.0, 0.5, 1.0], &settings.screen_shake)
+        }
+        OptionButton::HitStop => settings.hit_stop = next_in(&[0.0, 0.5, 1.0], &settings.hit_stop),
+        OptionButton::Squash => settings.squash = next_in(&[0.0, 0.5, 1.0], &settings.squash),
+        OptionButton::Flash => settings.flash = next_in(&[0.0, 0.5, 1.0], &settings.flash),
+        OptionButton::Particles => settings.particles = !settings.particles,
+        OptionButton::Language => {
+            settings.language = next_in(LANGUAGES, &settings.language.as_str()).to_string()
+        }
+        OptionButton::Theme => {
+            let themes = crate::theme::available_themes();
+            if !themes.is_empty() {
+                settings.theme = next_in(&themes, &settings.theme)
+            }
+        }
+        OptionButton::TransitionDuration => {
+            settings.transition_duration =
+                next_in(&[0.0, 0.25, 0.5, 1.0], &settings.transition_duration)
+        }
+        OptionButton::Back => (),
+    }
+}
+
+pub fn setup_options_menu(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    settings: Res<Settings>,
+) {
+    let text_style = theme.text_style(ThemedText::Small);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceAround,
+                ..Default::default()
+            },
+            color: UiColor(theme.theme.backdrop),
+            ..Default::default()
+        })
+        .insert(ThemedBackdrop)
+        .with_children(|grandparent| {
+            grandparent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.0), Val::Percent(95.0)),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceAround,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for button in OPTION_BUTTONS {
+                        parent
+                            .spawn_bundle(ButtonBundle::default())
+                            .insert(button)
+                            .with_children(|btn| {
+                                btn.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        "",
+                                        text_style.clone(),
+                                        TextAlignment {
+                                            vertical: VerticalAlign::Center,
+                                            horizontal: HorizontalAlign::Center,
+                                        },
+                                    ),
+                                    style: Style {
+                                        flex_grow: 0.0,
+                                        ..Default::default()
+                                    },
+                                    ..Default::default()
+                                })
+                                .insert(option_label(button, &settings))
+                                .insert(ThemedText::Small);
+                            });
+                    }
+                });
+        });
+}
+
+fn menu_update(
+    mut transitions: ResMut<Transitions>,
+    mut settings: ResMut<Settings>,
+    mut interaction_query: Query<(&Interaction, &OptionButton, &Children), ChangedButton>,
+    mut label_query: Query<&mut LocalizedText>,
+) {
+    for (interaction, button, children) in interaction_query.iter_mut() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let OptionButton::Back = button {
+            transitions.set(GameState::MainMenu, TransitionStyle::Fade);
+            continue;
+        }
+        // Settings are applied live by `settings::apply_settings_system` via change detection.
+        cycle_option(*button, &mut settings);
+        match children.first().map(|&label| label_query.get_mut(label)) {
+            Some(Ok(mut label)) => *label = option_label(*button, &settings),
+            _ => warn!("Option button {:?} has no label", button),
+        }
+    }
+}
+
+/// Keeps the settings however the menu is left, not only through the Back button.
+fn save_settings(settings: Res<Settings>) {
+    if let Err(e) = settings.save() {
+        warn!("Failed to save settings: {}", e);
+    }
+}
+
+fn teardown(
+    mut commands: Commands,
+    menu_nodes: Query<(Entity, &Node), Without<TransitionOverlay>>,
+) {
+    for (e, _n) in menu_nodes.iter() {
+        commands.entity(e).despawn();
+    }
+}
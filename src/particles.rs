@@ -0,0 +1,419 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::events::{BrickDestroyed, PaddleHit};
+use crate::gameplay::Ball;
+use crate::settings::Settings;
+use crate::state::GameState;
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Level)
+        .with_system(gameplay_bursts_system)
+        .with_system(spawn_bursts_system)
+        .with_system(ball_trail_system)
+        .with_system(update_particles_system)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Level).with_system(teardown)
+}
+
+/// How one kind of effect spawns its particles.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EmitterSpec {
+    /// Particles per burst, or per second for continuous emitters such as the ball trail.
+    pub count: u32,
+    /// Range of initial speeds, in world units per second.
+    pub speed: (f32, f32),
+    /// Central direction of travel in degrees; 0 is right and 90 is up.
+    pub direction: f32,
+    /// Total angle, in degrees, that directions are spread over around `direction`.
+    pub spread: f32,
+    /// Vertical acceleration; negative values pull particles down.
+    pub gravity: f32,
+    /// Range of lifetimes in seconds.
+    pub lifetime: (f32, f32),
+    /// Range of particle sizes, in world units.
+    pub size: (f32, f32),
+    /// Tint for the particles. Particles that copy a sprite keep its colors when this is `None`.
+    #[serde(default)]
+    pub color: Option<Color>,
+    /// Whether particles fade out over their lifetime.
+    pub fade: bool,
+}
+
+/// Emitter settings, read from `assets/effects/particles.ron`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ParticleConfig {
+    /// Upper limit on live particles; new ones are dropped beyond it.
+    pub max_particles: usize,
+    pub brick_debris: EmitterSpec,
+    pub ball_trail: EmitterSpec,
+    pub paddle_sparks: EmitterSpec,
+}
+
+impl ParticleConfig {
+    pub fn load() -> ParticleConfig {
+        let path = "assets/effects/particles.ron";
+        match std::fs::File::open(path) {
+            Ok(file) => ron::de::from_reader(file).unwrap_or_else(|e| {
+                warn!("Failed to parse {}: {}", path, e);
+                ParticleConfig::default()
+            }),
+            Err(e) => {
+                warn!("Failed to open {}: {}", path, e);
+                ParticleConfig::default()
+            }
+        }
+    }
+}
+
+impl Default for ParticleConfig {
+    fn default() -> ParticleConfig {
+        ParticleConfig {
+            max_particles: 400,
+            brick_debris: EmitterSpec {
+                count: 10,
+                speed: (80.0, 260.0),
+                direction: 90.0,
+                spread: 360.0,
+                gravity: -700.0,
+                lifetime: (0.4, 0.8),
+                size: (6.0, 14.0),
+                color: None,
+                fade: true,
+            },
+            ball_trail: EmitterSpec {
+                count: 40,
+                speed: (0.0, 10.0),
+                direction: 90.0,
+                spread: 360.0,
+                gravity: 0.0,
+                lifetime: (0.15, 0.25),
+                size: (10.0, 18.0),
+                color: None,
+                fade: true,
+            },
+            paddle_sparks: EmitterSpec {
+                count: 8,
+                speed: (150.0, 350.0),
+                direction: 90.0,
+                spread: 120.0,
+                gravity: -500.0,
+                lifetime: (0.15, 0.35),
+                size: (3.0, 6.0),
+                color: Some(Color::rgb(1.0, 0.9, 0.4)),
+                fade: true,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterKind {
+    BrickDebris,
+    PaddleSparks,
+}
+
+/// Request for a one-off burst of particles.
+pub struct ParticleBurst {
+    pub kind: EmitterKind,
+    pub position: Vec3,
+    /// Atlas region to draw the particles with; plain squares are used when `None`.
+    pub sprite: Option<(Handle<TextureAtlas>, usize)>,
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    lifetime: Timer,
+    fade: bool,
+    alpha: f32,
+}
+
+/// Effects are purely cosmetic, so they use their own random numbers rather than `GameRng`.
+fn spawn_particle(
+    commands: &mut Commands,
+    spec: &EmitterSpec,
+    position: Vec3,
+    sprite: Option<&(Handle<TextureAtlas>, usize)>,
+    rng: &mut impl Rng,
+) {
+    let range = |(min, max): (f32, f32), rng: &mut dyn rand::RngCore| {
+        if max > min {
+            rng.gen_range(min..max)
+        } else {
+            min
+        }
+    };
+    let angle = (spec.direction + (rng.gen::<f32>() - 0.5) * spec.spread).to_radians();
+    let speed = range(spec.speed, rng);
+    let size = range(spec.size, rng);
+    let color = spec.color.unwrap_or(Color::WHITE);
+    let particle = Particle {
+        velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+        gravity: spec.gravity,
+        lifetime: Timer::from_seconds(range(spec.lifetime, rng), false),
+        fade: spec.fade,
+        alpha: color.a(),
+    };
+    let transform = Transform::from_translation(position);
+    match sprite {
+        Some((atlas, index)) => commands
+            .spawn_bundle(SpriteSheetBundle {
+                texture_atlas: atlas.clone(),
+                sprite: TextureAtlasSprite {
+                    index: *index,
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..Default::default()
+                },
+                transform,
+                ..Default::default()
+            })
+            .insert(particle),
+        None => commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..Default::default()
+                },
+                transform,
+                ..Default::default()
+            })
+            .insert(particle),
+    };
+}
+
+/// Throws debris off broken bricks and sparks off the paddle.
+fn gameplay_bursts_system(
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut paddle_hits: EventReader<PaddleHit>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    for brick in destroyed.iter() {
+        bursts.send(ParticleBurst {
+            kind: EmitterKind::BrickDebris,
+            position: brick.position,
+            sprite: brick.sprite.clone(),
+        });
+    }
+    for hit in paddle_hits.iter() {
+        bursts.send(ParticleBurst {
+            kind: EmitterKind::PaddleSparks,
+            position: hit.position,
+            sprite: None,
+        });
+    }
+}
+
+fn spawn_bursts_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    config: Res<ParticleConfig>,
+    mut bursts: EventReader<ParticleBurst>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    if !settings.particles {
+        bursts.iter().for_each(drop);
+        return;
+    }
+    let mut live = particle_query.iter().count();
+    let mut rng = rand::thread_rng();
+    for burst in bursts.iter() {
+        let spec = match burst.kind {
+            EmitterKind::BrickDebris => &config.brick_debris,
+            EmitterKind::PaddleSparks => &config.paddle_sparks,
+        };
+        for _ in 0..spec.count {
+            if live >= config.max_particles {
+                return;
+            }
+            spawn_particle(
+                &mut commands,
+                spec,
+                burst.position + Vec3::Z * 2.0,
+                burst.sprite.as_ref(),
+                &mut rng,
+            );
+            live += 1;
+        }
+    }
+}
+
+/// Leaves a short trail of fading copies of the ball behind it.
+fn ball_trail_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    config: Res<ParticleConfig>,
+    mut owed: Local<f32>,
+    ball_query: Query<(&Transform, &Handle<TextureAtlas>, &TextureAtlasSprite), With<Ball>>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    if !settings.particles {
+        return;
+    }
+    *owed += config.ball_trail.count as f32 * time.delta_seconds();
+    let mut live = particle_query.iter().count();
+    let mut rng = rand::thread_rng();
+    while *owed >= 1.0 {
+        *owed -= 1.0;
+        for (transform, atlas, sprite) in ball_query.iter() {
+            if live >= config.max_particles {
+                continue;
+            }
+            let position = transform.translation - Vec3::Z * 0.5;
+            let source = (atlas.clone(), sprite.index);
+            spawn_particle(
+                &mut commands,
+                &config.ball_trail,
+                position,
+                Some(&source),
+                &mut rng,
+            );
+            live += 1;
+        }
+    }
+}
+
+/// A particle and whichever kind of sprite it is drawn with.
+type ParticleSprite<'a> = (
+    Entity,
+    &'a mut Particle,
+    &'a mut Transform,
+    Option<&'a mut Sprite>,
+    Option<&'a mut TextureAtlasSprite>,
+);
+
+fn update_particles_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    hit_stop: Res<crate::feel::HitStop>,
+    mut particle_query: Query<ParticleSprite>,
+) {
+    if hit_stop.is_frozen() {
+        return;
+    }
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, sprite, atlas_sprite) in particle_query.iter_mut() {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        particle.velocity.y += particle.gravity * delta;
+        transform.translation += particle.velocity.extend(0.0) * delta;
+        if particle.fade {
+            let alpha = particle.alpha * particle.lifetime.percent_left();
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_a(alpha);
+            }
+            if let Some(mut sprite) = atlas_sprite {
+                sprite.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+fn teardown(mut commands: Commands, particle_query: Query<Entity, With<Particle>>) {
+    for entity in particle_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::Events;
+
+    fn app(settings: Settings, config: ParticleConfig) -> App {
+        let mut app = App::new();
+        app.insert_resource(settings)
+            .insert_resource(config)
+            .add_event::<ParticleBurst>()
+            .add_system(spawn_bursts_system);
+        app
+    }
+
+    fn burst(app: &mut App, kind: EmitterKind) {
+        app.world
+            .resource_mut::<Events<ParticleBurst>>()
+            .send(ParticleBurst {
+                kind,
+                position: Vec3::ZERO,
+                sprite: None,
+            });
+    }
+
+    fn particles(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<Particle>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    #[test]
+    fn the_shipped_config_parses() {
+        let file = std::fs::File::open("assets/effects/particles.ron").unwrap();
+        let config: ParticleConfig = ron::de::from_reader(file).unwrap();
+        assert!(config.max_particles > 0);
+    }
+
+    #[test]
+    fn bursts_stop_at_the_particle_limit() {
+        let config = ParticleConfig {
+            max_particles: 15,
+            ..Default::default()
+        };
+        let debris = config.brick_debris.count as usize;
+        let mut app = app(Settings::default(), config);
+        burst(&mut app, EmitterKind::BrickDebris);
+        app.update();
+        assert_eq!(particles(&mut app), debris);
+        burst(&mut app, EmitterKind::BrickDebris);
+        app.update();
+        assert_eq!(particles(&mut app), 15);
+    }
+
+    #[test]
+    fn nothing_spawns_with_particles_off() {
+        let settings = Settings {
+            particles: false,
+            ..Default::default()
+        };
+        let mut app = app(settings, ParticleConfig::default());
+        burst(&mut app, EmitterKind::PaddleSparks);
+        app.update();
+        assert_eq!(particles(&mut app), 0);
+        // the burst was read, so it does not turn up once particles are back on
+        app.world.resource_mut::<Settings>().particles = true;
+        app.update();
+        assert_eq!(particles(&mut app), 0);
+    }
+
+    #[test]
+    fn particles_are_drawn_from_the_spec_ranges() {
+        let spec = ParticleConfig::default().paddle_sparks;
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            spawn_particle(&mut commands, &spec, Vec3::ZERO, None, &mut rng);
+        }
+        queue.apply(&mut world);
+        let half_spread = (spec.spread / 2.0).to_radians();
+        for (particle, sprite) in world.query::<(&Particle, &Sprite)>().iter(&world) {
+            let speed = particle.velocity.length();
+            assert!(speed >= spec.speed.0 - 0.01 && speed <= spec.speed.1 + 0.01);
+            let angle = particle.velocity.y.atan2(particle.velocity.x);
+            assert!((angle - spec.direction.to_radians()).abs() <= half_spread + 0.01);
+            let size = sprite.custom_size.unwrap().x;
+            assert!(size >= spec.size.0 && size <= spec.size.1);
+        }
+    }
+}
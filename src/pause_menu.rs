@@ -1,9 +1,10 @@
-use bevy::{
-    input::{keyboard::KeyboardInput, ElementState},
-    prelude::*,
-};
+use bevy::prelude::*;
 
+use crate::controls::PlayerInput;
+use crate::locale::LocalizedText;
 use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedText};
+use crate::transition::{TransitionStyle, Transitions};
 
 #[derive(Component)]
 struct PauseUI;
@@ -19,16 +20,12 @@ pub fn update_system_set() -> SystemSet {
 pub fn exit_system_set() -> SystemSet {
     SystemSet::on_exit(GameState::PauseMenu).with_system(teardown)
 }
-pub fn setup_pause_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let text_style = TextStyle {
-        font: asset_server.load("font/FiraSans-Light.ttf"),
-        font_size: 60.0,
-        color: Color::rgb(0.71, 0.8, 0.4),
-    };
+pub fn setup_pause_ui(mut commands: Commands, theme: Res<ActiveTheme>) {
+    let text_style = theme.text_style(ThemedText::Title);
     commands
         .spawn_bundle(TextBundle {
             text: Text::with_section(
-                "Press Any Key to Continue".to_string(),
+                "",
                 text_style.clone(),
                 TextAlignment {
                     vertical: VerticalAlign::Center,
@@ -47,18 +44,14 @@ pub fn setup_pause_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             },
             ..Default::default()
         })
-        .insert(PauseUI);
+        .insert(PauseUI)
+        .insert(LocalizedText::new("pause.continue"))
+        .insert(ThemedText::Title);
 }
 
-fn get_keyboard_input(
-    mut keyboard_input: EventReader<KeyboardInput>,
-    mut state: ResMut<State<GameState>>,
-) {
-    if keyboard_input
-        .iter()
-        .any(|evt| evt.state == ElementState::Pressed)
-    {
-        state.pop().expect("Failed to open pause menu");
+fn get_keyboard_input(input: Res<PlayerInput>, mut transitions: ResMut<Transitions>) {
+    if input.any_key {
+        transitions.pop(TransitionStyle::Fade);
     }
 }
 fn teardown(mut commands: Commands, pause_text: Query<(Entity, &PauseUI)>) {
@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+/// Size of the letterbox bars; large enough to cover any window outside the playfield.
+const BAR_SIZE: f32 = 10000.0;
+
+/// The logical play area, in world units, centered on the origin.
+///
+/// All gameplay coordinates are expressed in playfield space; the main camera is scaled so the
+/// whole playfield fits the window and the remaining space is letterboxed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playfield {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Playfield {
+    pub fn from_level(level: &crate::level::Level) -> Playfield {
+        Playfield {
+            width: level.level_width as f32,
+            height: level.level_height as f32,
+        }
+    }
+
+    pub fn half_width(&self) -> f32 {
+        self.width / 2.
+    }
+
+    pub fn half_height(&self) -> f32 {
+        self.height / 2.
+    }
+
+    /// World units per window pixel needed to fit the playfield inside a window of this size.
+    pub fn scale_to_fit(&self, window_width: f32, window_height: f32) -> f32 {
+        if window_width <= 0.0 || window_height <= 0.0 {
+            return 1.0;
+        }
+        (self.width / window_width).max(self.height / window_height)
+    }
+
+    /// Converts a window position (origin bottom left, in pixels) to playfield coordinates.
+    pub fn window_to_playfield(
+        &self,
+        position: Vec2,
+        window_width: f32,
+        window_height: f32,
+    ) -> Vec2 {
+        let scale = self.scale_to_fit(window_width, window_height);
+        (position - Vec2::new(window_width, window_height) / 2.) * scale
+    }
+}
+
+impl Default for Playfield {
+    fn default() -> Playfield {
+        Playfield {
+            width: 1100.,
+            height: 720.,
+        }
+    }
+}
+
+/// Marks the camera that renders the playfield.
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Component, Clone, Copy)]
+pub enum LetterboxBar {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+pub fn spawn_letterbox(commands: &mut Commands) {
+    for bar in [
+        LetterboxBar::Left,
+        LetterboxBar::Right,
+        LetterboxBar::Top,
+        LetterboxBar::Bottom,
+    ] {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::BLACK,
+                    custom_size: Some(Vec2::new(BAR_SIZE, BAR_SIZE)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(bar)
+            .insert(crate::theme::ThemedLetterbox);
+    }
+}
+
+/// Scales the main camera so the playfield fits the window, and moves the letterbox bars to
+/// hide everything outside of it.
+pub fn fit_playfield_system(
+    playfield: Res<Playfield>,
+    windows: Res<Windows>,
+    mut camera_query: Query<&mut OrthographicProjection, With<MainCamera>>,
+    mut bar_query: Query<(&LetterboxBar, &mut Transform)>,
+) {
+    if let Some(window) = windows.get_primary() {
+        let scale = playfield.scale_to_fit(window.width(), window.height());
+        for mut projection in camera_query.iter_mut() {
+            if (projection.scale - scale).abs() > f32::EPSILON {
+                projection.scale = scale;
+            }
+        }
+    }
+    if !playfield.is_changed() {
+        return;
+    }
+    let offset_x = playfield.half_width() + BAR_SIZE / 2.;
+    let offset_y = playfield.half_height() + BAR_SIZE / 2.;
+    for (bar, mut transform) in bar_query.iter_mut() {
+        let position = match bar {
+            LetterboxBar::Left => Vec2::new(-offset_x, 0.0),
+            LetterboxBar::Right => Vec2::new(offset_x, 0.0),
+            LetterboxBar::Top => Vec2::new(0.0, offset_y),
+            LetterboxBar::Bottom => Vec2::new(0.0, -offset_y),
+        };
+        *transform = Transform::from_xyz(position.x, position.y, 10.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYFIELD: Playfield = Playfield {
+        width: 1100.,
+        height: 720.,
+    };
+
+    #[test]
+    fn wide_windows_are_letterboxed_at_the_sides() {
+        // the height fills the window, leaving bars left and right
+        assert_eq!(PLAYFIELD.scale_to_fit(2000., 360.), 2.);
+        assert_eq!(PLAYFIELD.scale_to_fit(2200., 1440.), 0.5);
+    }
+
+    #[test]
+    fn tall_windows_are_letterboxed_at_the_top_and_bottom() {
+        assert_eq!(PLAYFIELD.scale_to_fit(550., 2000.), 2.);
+    }
+
+    #[test]
+    fn empty_windows_keep_the_scale() {
+        assert_eq!(PLAYFIELD.scale_to_fit(0., 720.), 1.);
+        assert_eq!(PLAYFIELD.scale_to_fit(1100., -1.), 1.);
+    }
+
+    #[test]
+    fn window_positions_map_into_the_playfield() {
+        let (width, height) = (2200., 1440.);
+        let center = Vec2::new(width, height) / 2.;
+        assert_eq!(
+            PLAYFIELD.window_to_playfield(center, width, height),
+            Vec2::ZERO
+        );
+        assert_eq!(
+            PLAYFIELD.window_to_playfield(Vec2::ZERO, width, height),
+            Vec2::new(-PLAYFIELD.half_width(), -PLAYFIELD.half_height())
+        );
+        // outside the playfield, inside the letterbox
+        assert_eq!(
+            PLAYFIELD.window_to_playfield(Vec2::new(0., 720.), 4400., 1440.),
+            Vec2::new(-1100., 0.)
+        );
+    }
+}
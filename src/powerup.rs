@@ -0,0 +1,313 @@
+//! Power-ups that bricks drop at random, caught with the paddle.
+//!
+//! This is gameplay: a wide paddle and a slow ball make a level easier, so the drops come from
+//! `GameRng` and everything runs on `GameTime`, the same as the ball.
+
+use bevy::math::const_vec2;
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
+use rand::Rng;
+
+use crate::clock::GameTime;
+use crate::events::BrickDestroyed;
+use crate::gameplay::{Collider, GameRng, GameplaySystem, Paddle};
+use crate::playfield::Playfield;
+use crate::state::GameState;
+
+/// Chance that a destroyed brick drops a power-up.
+const DROP_CHANCE: f64 = 0.1;
+const PICKUP_SPEED: f32 = 150.0;
+const PICKUP_SIZE: Vec2 = const_vec2!([40.0, 20.0]);
+pub const POWERUP_SECONDS: f32 = 10.0;
+/// Width of the paddle while `WidePaddle` is active, relative to its normal width.
+const WIDE_PADDLE_SCALE: f32 = 1.5;
+/// Speed of the ball while `SlowBall` is active, relative to its normal speed.
+const SLOW_BALL_FACTOR: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    WidePaddle,
+    SlowBall,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 2] = [PowerUpKind::WidePaddle, PowerUpKind::SlowBall];
+
+    /// String-table key of the power-up's display name.
+    pub fn name_key(&self) -> &'static str {
+        match self {
+            PowerUpKind::WidePaddle => "powerup.wide_paddle",
+            PowerUpKind::SlowBall => "powerup.slow_ball",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            PowerUpKind::WidePaddle => Color::rgb(0.3, 0.6, 1.0),
+            PowerUpKind::SlowBall => Color::rgb(1.0, 0.8, 0.2),
+        }
+    }
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Level)
+        .with_system(tick_powerups_system)
+        .with_system(pickup_system)
+        .with_system(drop_pickups_system.after(GameplaySystem::Bricks))
+        .with_system(paddle_width_system)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Level).with_system(teardown)
+}
+
+/// Power-ups currently in effect, each with the time it has left.
+#[derive(Default)]
+pub struct ActivePowerUps {
+    pub timers: Vec<(PowerUpKind, Timer)>,
+}
+
+impl ActivePowerUps {
+    /// Starts a power-up, or restarts its timer if it is already running.
+    pub fn activate(&mut self, kind: PowerUpKind, seconds: f32) {
+        self.timers.retain(|(k, _)| *k != kind);
+        self.timers
+            .push((kind, Timer::from_seconds(seconds, false)));
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.timers.iter().any(|(k, _)| *k == kind)
+    }
+
+    /// Multiplier applied to the ball's velocity when moving it.
+    pub fn ball_speed_factor(&self) -> f32 {
+        if self.is_active(PowerUpKind::SlowBall) {
+            SLOW_BALL_FACTOR
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A power-up falling towards the paddle, dropped by a destroyed brick.
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PowerUpKind,
+}
+
+/// Sent when the paddle catches a falling power-up.
+pub struct PowerUpCollected;
+
+/// Rolls for a power-up drop wherever a brick was destroyed.
+fn drop_pickups_system(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    mut destroyed: EventReader<BrickDestroyed>,
+) {
+    for brick in destroyed.iter() {
+        maybe_drop_pickup(&mut commands, &mut rng, brick.position);
+    }
+}
+
+fn maybe_drop_pickup(commands: &mut Commands, rng: &mut GameRng, position: Vec3) {
+    if !rng.0.gen_bool(DROP_CHANCE) {
+        return;
+    }
+    let kind = PowerUpKind::ALL[rng.0.gen_range(0..PowerUpKind::ALL.len())];
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(PICKUP_SIZE),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(position.x, position.y, 1.0),
+            ..Default::default()
+        })
+        .insert(Pickup { kind });
+}
+
+fn tick_powerups_system(time: Res<GameTime>, mut active: ResMut<ActivePowerUps>) {
+    if active.timers.is_empty() {
+        return;
+    }
+    for (_, timer) in active.timers.iter_mut() {
+        timer.tick(time.delta());
+    }
+    active.timers.retain(|(_, timer)| !timer.finished());
+}
+
+/// The paddle, as seen by pickups falling onto it.
+type PaddleCatcher = (With<Paddle>, Without<Pickup>);
+
+/// Moves falling pickups, activating them when they touch the paddle.
+fn pickup_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    playfield: Res<Playfield>,
+    mut active: ResMut<ActivePowerUps>,
+    mut collected: EventWriter<PowerUpCollected>,
+    mut pickup_query: Query<(Entity, &Pickup, &mut Transform)>,
+    paddle_query: Query<(&Collider, &Transform), PaddleCatcher>,
+) {
+    for (entity, pickup, mut transform) in pickup_query.iter_mut() {
+        transform.translation.y -= PICKUP_SPEED * time.delta_seconds();
+        let caught = paddle_query.iter().any(|(collider, paddle_transform)| {
+            collide(
+                transform.translation,
+                PICKUP_SIZE,
+                paddle_transform.translation,
+                collider.get_size(),
+            )
+            .is_some()
+        });
+        if caught {
+            active.activate(pickup.kind, POWERUP_SECONDS);
+            collected.send(PowerUpCollected);
+            commands.entity(entity).despawn();
+        } else if transform.translation.y < -playfield.half_height() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Stretches the paddle and its collider while `WidePaddle` is active.
+fn paddle_width_system(
+    active: Res<ActivePowerUps>,
+    mut paddle_query: Query<(&mut Collider, &mut Transform), With<Paddle>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    let scale = if active.is_active(PowerUpKind::WidePaddle) {
+        WIDE_PADDLE_SCALE
+    } else {
+        1.0
+    };
+    for (mut collider, mut transform) in paddle_query.iter_mut() {
+        if transform.scale.x != scale {
+            if let Collider::Paddle { size } = collider.as_mut() {
+                size.x *= scale / transform.scale.x;
+            }
+            transform.scale.x = scale;
+        }
+    }
+}
+
+fn teardown(mut commands: Commands, pickup_query: Query<Entity, With<Pickup>>) {
+    for entity in pickup_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::Events;
+    use std::time::Duration;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(GameTime::fixed(Duration::from_millis(100)))
+            .add_system_to_stage(CoreStage::First, crate::clock::update_game_time_system)
+            .init_resource::<ActivePowerUps>()
+            .init_resource::<Playfield>()
+            .add_event::<PowerUpCollected>();
+        app
+    }
+
+    #[test]
+    fn activating_again_restarts_the_timer() {
+        let mut active = ActivePowerUps::default();
+        active.activate(PowerUpKind::SlowBall, 10.0);
+        active.timers[0].1.tick(Duration::from_secs(8));
+        active.activate(PowerUpKind::SlowBall, 10.0);
+        assert_eq!(active.timers.len(), 1);
+        assert_eq!(active.timers[0].1.elapsed_secs(), 0.0);
+        assert_eq!(active.ball_speed_factor(), SLOW_BALL_FACTOR);
+    }
+
+    #[test]
+    fn power_ups_run_out() {
+        let mut app = app();
+        app.add_system(tick_powerups_system);
+        app.world
+            .resource_mut::<ActivePowerUps>()
+            .activate(PowerUpKind::WidePaddle, 0.25);
+        app.update();
+        app.update();
+        assert!(app
+            .world
+            .resource::<ActivePowerUps>()
+            .is_active(PowerUpKind::WidePaddle));
+        app.update();
+        assert!(app.world.resource::<ActivePowerUps>().timers.is_empty());
+        assert_eq!(
+            app.world.resource::<ActivePowerUps>().ball_speed_factor(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn drops_are_the_same_for_the_same_seed() {
+        let drops = |seed| {
+            let mut app = app();
+            app.insert_resource(GameRng::from_seed(seed))
+                .add_event::<BrickDestroyed>()
+                .add_system(drop_pickups_system);
+            for _ in 0..100 {
+                app.world
+                    .resource_mut::<Events<BrickDestroyed>>()
+                    .send(BrickDestroyed {
+                        position: Vec3::ZERO,
+                        size: Vec2::ONE,
+                        sprite: None,
+                    });
+            }
+            app.update();
+            app.world
+                .query::<&Pickup>()
+                .iter(&app.world)
+                .map(|pickup| pickup.kind)
+                .collect::<Vec<_>>()
+        };
+        let first = drops(7);
+        assert!(!first.is_empty());
+        assert_eq!(first, drops(7));
+    }
+
+    #[test]
+    fn catching_a_pickup_widens_the_paddle() {
+        let mut app = app();
+        app.add_system(pickup_system.label("pickup"))
+            .add_system(paddle_width_system.after("pickup"));
+        let paddle = app
+            .world
+            .spawn()
+            .insert(Paddle::default())
+            .insert(Collider::Paddle {
+                size: Vec2::new(100.0, 20.0),
+            })
+            .insert(Transform::default())
+            .id();
+        app.world
+            .spawn()
+            .insert(Pickup {
+                kind: PowerUpKind::WidePaddle,
+            })
+            .insert(Transform::from_xyz(0.0, 20.0, 1.0));
+        app.update();
+        assert_eq!(app.world.query::<&Pickup>().iter(&app.world).count(), 0);
+        let collected = app.world.resource::<Events<PowerUpCollected>>();
+        assert_eq!(collected.get_reader().iter(collected).count(), 1);
+        assert_eq!(
+            app.world.get::<Transform>(paddle).unwrap().scale.x,
+            WIDE_PADDLE_SCALE
+        );
+        assert_eq!(
+            app.world.get::<Collider>(paddle).unwrap().get_size(),
+            Vec2::new(100.0 * WIDE_PADDLE_SCALE, 20.0)
+        );
+    }
+}
@@ -0,0 +1,730 @@
+//! Recording games and playing them back.
+//!
+//! A game is recorded from the moment its first level is entered until play ends, then saved
+//! to [`replay_dir`]. A [`Replay`] holds where the game started, its seed, the settings that
+//! change how it plays, and the input and length of every frame, so playing it back runs the
+//! game through exactly the same frames. Runs of identical frames are stored once, which keeps
+//! held keys and idle time in the pause menu small.
+//!
+//! Playback runs in the normal game view. Besides pausing, it can fast-forward and seek by
+//! running the game's schedule several times per displayed frame, see [`repeat_frames`].
+
+use anyhow::{bail, Context};
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::attract::Attract;
+use crate::clock::GameTime;
+use crate::controls::PlayerInput;
+use crate::gameplay::{GameRng, GameplaySystem, Score};
+use crate::level::Level;
+use crate::locale::LocalizedText;
+use crate::settings::Settings;
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedText};
+use crate::transition::{TransitionStyle, Transitions};
+use crate::BlocksConfig;
+
+/// Bumped whenever a change to the game makes older replays play out differently.
+pub const FORMAT_VERSION: u32 = 4;
+
+/// Replays shown in the replays menu, newest first.
+pub const MAX_LISTED: usize = 8;
+
+/// Playback speeds the fast-forward key cycles through.
+const SPEEDS: [u32; 4] = [1, 2, 4, 8];
+
+/// Frames skipped by one press of a seek key, five seconds at 60 Hz.
+const SEEK_FRAMES: usize = 300;
+
+/// Frames played per displayed frame while seeking.
+const SEEK_SPEED: u32 = 32;
+
+const PAUSE_KEY: KeyCode = KeyCode::Space;
+const SPEED_KEY: KeyCode = KeyCode::F;
+const SEEK_BACK_KEY: KeyCode = KeyCode::Left;
+const SEEK_FORWARD_KEY: KeyCode = KeyCode::Right;
+const STOP_KEY: KeyCode = KeyCode::Back;
+
+/// A recorded game.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub version: u32,
+    /// Index of the level the game started on.
+    pub level: usize,
+    /// Name of that level, to notice replays of levels that have changed since.
+    pub level_name: String,
+    pub seed: u64,
+    pub lives: i32,
+    pub points: i32,
+    pub settings: ReplaySettings,
+    /// Every frame in order, as runs of `(count, frame)`.
+    pub frames: Vec<(u32, Frame)>,
+    /// The score the game ended with.
+    pub result: FinalScore,
+}
+
+/// The settings that change how a game plays out, rather than how it looks or sounds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ReplaySettings {
+    pub transition_duration: f32,
+}
+
+impl ReplaySettings {
+    pub fn from_settings(settings: &Settings) -> ReplaySettings {
+        ReplaySettings {
+            transition_duration: settings.transition_duration,
+        }
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        settings.transition_duration = self.transition_duration;
+    }
+}
+
+/// One frame of a recorded game.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Frame {
+    /// Length of the frame in nanoseconds.
+    pub nanos: u64,
+    pub input: PlayerInput,
+}
+
+/// The parts of the score compared after playing a replay back.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FinalScore {
+    pub level: usize,
+    pub points: i32,
+    pub lives: i32,
+    pub bricks_left: usize,
+    pub combo: u32,
+}
+
+impl From<&Score> for FinalScore {
+    fn from(score: &Score) -> FinalScore {
+        FinalScore {
+            level: score.current_level,
+            points: score.points,
+            lives: score.lives,
+            bricks_left: score.bricks_left,
+            combo: score.combo,
+        }
+    }
+}
+
+impl Replay {
+    /// An empty recording of a game starting with `score`.
+    pub fn new(level: &Level, score: &Score, seed: u64, settings: &Settings) -> Replay {
+        Replay {
+            version: FORMAT_VERSION,
+            level: score.current_level,
+            level_name: level.name.clone(),
+            seed,
+            lives: score.lives,
+            points: score.points,
+            settings: ReplaySettings::from_settings(settings),
+            frames: Vec::new(),
+            result: FinalScore::default(),
+        }
+    }
+
+    /// Appends a frame, extending the last run if it is the same.
+    pub fn push(&mut self, frame: Frame) {
+        match self.frames.last_mut() {
+            Some((count, last)) if *last == frame => *count += 1,
+            _ => self.frames.push((1, frame)),
+        }
+    }
+
+    /// Every frame in order, with the runs expanded.
+    pub fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        self.frames
+            .iter()
+            .flat_map(|&(count, frame)| (0..count).map(move |_| frame))
+    }
+
+    /// Number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.iter().map(|&(count, _)| count as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// How long the recorded game took.
+    pub fn duration(&self) -> Duration {
+        let nanos = self
+            .frames
+            .iter()
+            .map(|&(count, frame)| count as u64 * frame.nanos)
+            .sum();
+        Duration::from_nanos(nanos)
+    }
+
+    /// Whether the replay can be played back on `levels`.
+    pub fn check(&self, levels: &[Level]) -> Result<(), String> {
+        if self.version != FORMAT_VERSION {
+            return Err(format!(
+                "The replay is from version {} of the game, this is version {}",
+                self.version, FORMAT_VERSION
+            ));
+        }
+        match levels.get(self.level) {
+            Some(level) if level.name == self.level_name => Ok(()),
+            _ => Err(format!(
+                "The replay starts on level {} \"{}\", which is not in this game",
+                self.level + 1,
+                self.level_name
+            )),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Replay> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        ron::de::from_reader(file).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, ron::ser::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Where finished games are saved, e.g. `~/.local/share/bevy_blocks/replays` on Linux.
+pub fn replay_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("bevy_blocks").join("replays"))
+}
+
+/// The newest replays in `dir` that could be read, newest first.
+pub fn list(dir: &Path) -> Vec<(PathBuf, Replay)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_string_lossy().ends_with(".replay.ron"))
+        .collect();
+    // files are named after the time they were saved
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+    paths
+        .into_iter()
+        .filter_map(|path| match Replay::load(&path) {
+            Ok(replay) => Some((path, replay)),
+            Err(e) => {
+                warn!("{:#}", e);
+                None
+            }
+        })
+        .take(MAX_LISTED)
+        .collect()
+}
+
+/// The game being recorded.
+#[derive(Default)]
+pub struct Recorder {
+    session: Option<Replay>,
+    /// Set on the frame recording starts, which is held still and not recorded.
+    starting: bool,
+    /// Where finished recordings are saved; they are only kept in `last` when `None`.
+    pub save_to: Option<PathBuf>,
+    /// The last finished recording.
+    pub last: Option<Replay>,
+}
+
+impl Recorder {
+    pub fn saving_to(dir: Option<PathBuf>) -> Recorder {
+        Recorder {
+            save_to: dir,
+            ..Default::default()
+        }
+    }
+
+    /// Drops the game being recorded, for when something outside the player's input changed
+    /// it. Recording starts over when the next level is entered.
+    pub fn discard(&mut self) {
+        self.session = None;
+    }
+}
+
+/// The replay being played back.
+#[derive(Default)]
+pub struct Playback {
+    replay: Option<Replay>,
+    frames: Vec<Frame>,
+    /// Index of the next frame to play, from the moment the replay's level is entered.
+    next: Option<usize>,
+    /// Game time played so far.
+    elapsed: Duration,
+    paused: bool,
+    speed: u32,
+    seek_to: Option<usize>,
+    /// The player's own settings, put back once playback ends.
+    saved_settings: Option<ReplaySettings>,
+    /// No more frames are played, though the game is still cleaned up once it is left.
+    stopped: bool,
+}
+
+impl Playback {
+    /// Plays `replay` once its level is entered.
+    pub fn start(&mut self, replay: Replay) {
+        *self = Playback {
+            frames: replay.frames().collect(),
+            replay: Some(replay),
+            speed: SPEEDS[0],
+            ..Default::default()
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Stops playing frames, leaving the rest of the game to the player's own input.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Whether every frame has been played.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.next, Some(next) if next >= self.frames.len())
+    }
+
+    /// How many times the game's schedule runs for every displayed frame.
+    pub fn updates_per_frame(&self) -> u32 {
+        match self.next {
+            _ if self.stopped => 1,
+            _ if self.seek_to.is_some() => SEEK_SPEED,
+            Some(_) if !self.paused => self.speed,
+            _ => 1,
+        }
+    }
+
+    /// Whether several frames of the replay are played per displayed frame.
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.updates_per_frame() > 1
+    }
+}
+
+/// Whether `state` is part of a game in progress.
+fn in_game(state: &GameState) -> bool {
+    matches!(state, GameState::Level | GameState::PauseMenu)
+}
+
+/// Ties the game to the replay's frames as soon as a level is entered.
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Level)
+        .with_system(start_session_system.before(GameplaySystem::Setup))
+        .with_system(setup_replay_hud)
+}
+
+/// Starts playing back or recording when the first level of a game is entered.
+///
+/// Both hold the first frame still, since how long it took depends on how the level was
+/// entered rather than on the game.
+#[allow(clippy::too_many_arguments)]
+fn start_session_system(
+    config: Res<BlocksConfig>,
+    levels: Res<Vec<Level>>,
+    mut settings: ResMut<Settings>,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
+    mut time: ResMut<GameTime>,
+    mut input: ResMut<PlayerInput>,
+    mut recorder: ResMut<Recorder>,
+    mut playback: ResMut<Playback>,
+) {
+    if let Some(replay) = playback.replay.clone() {
+        if playback.next.is_some() {
+            // a later level of the same game
+            return;
+        }
+        *score = Score::with_lives(replay.lives);
+        score.current_level = replay.level;
+        score.points = replay.points;
+        *rng = GameRng::from_seed(replay.seed);
+        if playback.saved_settings.is_none() {
+            playback.saved_settings = Some(ReplaySettings::from_settings(&settings));
+        }
+        if ReplaySettings::from_settings(&settings) != replay.settings {
+            replay.settings.apply(&mut settings);
+        }
+        playback.next = Some(0);
+        playback.elapsed = Duration::ZERO;
+    } else if recorder.session.is_none() {
+        let level = match levels.get(score.current_level) {
+            Some(level) => level,
+            None => return,
+        };
+        let seed = config.seed.unwrap_or_else(rand::random);
+        *rng = GameRng::from_seed(seed);
+        recorder.session = Some(Replay::new(level, &score, seed, &settings));
+        recorder.starting = true;
+    } else {
+        return;
+    }
+    time.set_delta(Duration::ZERO);
+    *input = PlayerInput::default();
+}
+
+/// Records the frame that just ran, and saves the recording once the game is over.
+pub fn record_frame_system(
+    state: Res<State<GameState>>,
+    time: Res<GameTime>,
+    input: Res<PlayerInput>,
+    score: Res<Score>,
+    mut recorder: ResMut<Recorder>,
+) {
+    let recorder = &mut *recorder;
+    let session = match recorder.session.as_mut() {
+        Some(session) => session,
+        None => return,
+    };
+    if recorder.starting {
+        recorder.starting = false;
+    } else {
+        session.push(Frame {
+            nanos: time.delta().as_nanos() as u64,
+            input: *input,
+        });
+    }
+    if in_game(state.current()) {
+        return;
+    }
+    let mut replay = match recorder.session.take() {
+        Some(replay) => replay,
+        None => return,
+    };
+    replay.result = FinalScore::from(&*score);
+    if let Some(dir) = &recorder.save_to {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("{}.replay.ron", seconds));
+        match replay.save(&path) {
+            Ok(()) => info!("Saved replay to {}", path.display()),
+            Err(e) => warn!("Failed to save replay to {}: {:#}", path.display(), e),
+        }
+    }
+    recorder.last = Some(replay);
+}
+
+/// Label of [`play_frame_system`], for systems that have the last word on the player's input.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlaybackSystem;
+
+/// Replaces this frame's length and the player's input with the next recorded frame.
+///
+/// While paused, frames are held still instead, but only between transitions: a paused frame
+/// must never fall between a state change being requested and the frame it was applied on.
+pub fn play_frame_system(
+    transitions: Res<Transitions>,
+    mut playback: ResMut<Playback>,
+    mut time: ResMut<GameTime>,
+    mut input: ResMut<PlayerInput>,
+) {
+    if !playback.is_active() || playback.stopped {
+        return;
+    }
+    // the player's own input plays no part in a replay
+    *input = PlayerInput::default();
+    let next = match playback.next {
+        Some(next) => next,
+        // seeking back restarts the level; nothing that happens until then counts
+        None if playback.seek_to.is_some() => {
+            time.set_delta(Duration::ZERO);
+            return;
+        }
+        None => return,
+    };
+    let seeking = matches!(playback.seek_to, Some(to) if next < to);
+    if !seeking {
+        playback.seek_to = None;
+    }
+    let held = playback.paused && !seeking && !transitions.is_busy();
+    let frame = match playback.frames.get(next) {
+        Some(frame) if !held => *frame,
+        _ => {
+            time.set_delta(Duration::ZERO);
+            return;
+        }
+    };
+    time.set_delta(Duration::from_nanos(frame.nanos));
+    *input = frame.input;
+    playback.next = Some(next + 1);
+    playback.elapsed += time.delta();
+}
+
+/// Reports whether the replay ended with the recorded score, and puts the player's settings
+/// back, once the game being played back is over.
+pub fn finish_playback_system(
+    state: Res<State<GameState>>,
+    score: Res<Score>,
+    mut settings: ResMut<Settings>,
+    mut playback: ResMut<Playback>,
+) {
+    let entered = playback.next.is_some() || playback.stopped;
+    if !entered || in_game(state.current()) {
+        return;
+    }
+    let played_out = playback.is_finished() && !playback.stopped;
+    if let (true, Some(replay)) = (played_out, &playback.replay) {
+        let result = FinalScore::from(&*score);
+        if result == replay.result {
+            info!("Replay ended with the recorded score");
+        } else {
+            warn!(
+                "Replay ended with {:?}, but was recorded with {:?}",
+                result, replay.result
+            );
+        }
+    }
+    if let Some(saved) = playback.saved_settings {
+        saved.apply(&mut settings);
+    }
+    *playback = Playback::default();
+}
+
+/// Pauses, fast-forwards, seeks and stops playback.
+pub fn playback_controls_system(
+    keys: Res<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut playback: ResMut<Playback>,
+    mut transitions: ResMut<Transitions>,
+) {
+    let next = match playback.next {
+        Some(next) if in_game(state.current()) => next,
+        _ => return,
+    };
+    if keys.just_pressed(PAUSE_KEY) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(SPEED_KEY) {
+        let index = SPEEDS.iter().position(|&speed| speed == playback.speed);
+        playback.speed = SPEEDS[index.map_or(0, |index| (index + 1) % SPEEDS.len())];
+    }
+    if keys.just_pressed(SEEK_FORWARD_KEY) {
+        playback.seek_to = Some((next + SEEK_FRAMES).min(playback.frames.len()));
+    }
+    if keys.just_pressed(SEEK_BACK_KEY) {
+        // frames cannot be undone, so play from the start up to the earlier frame
+        playback.seek_to = Some(next.saturating_sub(SEEK_FRAMES));
+        playback.next = None;
+        if *state.current() == GameState::PauseMenu {
+            transitions.pop(TransitionStyle::Cut);
+        }
+        transitions.restart(TransitionStyle::Cut);
+    }
+    if keys.just_pressed(STOP_KEY) {
+        transitions.replace(GameState::Replays, TransitionStyle::Fade);
+    }
+}
+
+/// Shows the position in the replay and the playback controls.
+#[derive(Component)]
+pub struct ReplayStatus;
+
+fn setup_replay_hud(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    playback: Res<Playback>,
+    attract: Option<Res<Attract>>,
+) {
+    // replays shown behind the main menu are not for controlling
+    let demo = matches!(attract, Some(attract) if attract.is_playing());
+    if !playback.is_active() || demo {
+        return;
+    }
+    let mut spawn_label = |key: &str, bottom: f32| {
+        commands
+            .spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "",
+                    theme.text_style(ThemedText::Small),
+                    TextAlignment::default(),
+                ),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(10.0),
+                        bottom: Val::Px(bottom),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(LocalizedText::new(key))
+            .insert(ThemedText::Small)
+            .id()
+    };
+    spawn_label("replay.help", 10.0);
+    let status = spawn_label("replay.playing", 40.0);
+    commands.entity(status).insert(ReplayStatus);
+}
+
+pub fn update_replay_hud_system(
+    playback: Res<Playback>,
+    mut status_query: Query<&mut LocalizedText, With<ReplayStatus>>,
+) {
+    let replay = match &playback.replay {
+        Some(replay) => replay,
+        None => return,
+    };
+    let clock = |time: Duration| {
+        let seconds = time.as_secs();
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+    let key = if playback.paused {
+        "replay.paused"
+    } else {
+        "replay.playing"
+    };
+    let updated = LocalizedText::new(key)
+        .with_arg("time", clock(playback.elapsed))
+        .with_arg("total", clock(replay.duration()))
+        .with_arg("speed", playback.speed);
+    for mut label in status_query.iter_mut() {
+        if *label != updated {
+            *label = updated.clone();
+        }
+    }
+}
+
+/// Run criterion for [`GameSchedule`](crate::GameSchedule) that runs it as many times per update
+/// as playback asks for, so fast-forwarding and seeking play several frames of the game per
+/// displayed frame.
+pub fn repeat_frames(playback: Option<Res<Playback>>, mut runs: Local<u32>) -> ShouldRun {
+    let times = playback.map_or(1, |playback| playback.updates_per_frame());
+    *runs += 1;
+    if *runs < times {
+        ShouldRun::YesAndCheckAgain
+    } else {
+        *runs = 0;
+        ShouldRun::Yes
+    }
+}
+
+/// Plays `replay` back without a window and returns the score it ends with.
+pub fn play_headless(replay: &Replay, config: BlocksConfig) -> anyhow::Result<FinalScore> {
+    let config = BlocksConfig {
+        start_level: replay.level,
+        starting_lives: replay.lives,
+        seed: Some(replay.seed),
+        ..config
+    };
+    let mut app = crate::headless::app(config)?;
+    if let Err(e) = replay.check(app.world.resource::<Vec<Level>>()) {
+        bail!(e);
+    }
+    app.world.resource_mut::<Playback>().start(replay.clone());
+    // the first update enters the level, then every update plays one frame
+    for _ in 0..=replay.len() {
+        app.update();
+    }
+    Ok(FinalScore::from(app.world.resource::<Score>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(nanos: u64, direction: f32) -> Frame {
+        Frame {
+            nanos,
+            input: PlayerInput {
+                direction,
+                ..Default::default()
+            },
+        }
+    }
+
+    const EMPTY_LEVEL: &str = "Level(level_width: 1100, level_height: 720, ball_velocity: 1.5, \
+                               block_margin: 0, block_width: 100, block_height: 40, blocks: [], \
+                               rows: [])";
+
+    fn replay() -> Replay {
+        Replay::new(
+            &ron::de::from_str(EMPTY_LEVEL).unwrap(),
+            &Score::new(),
+            7,
+            &Settings::default(),
+        )
+    }
+
+    #[test]
+    fn identical_frames_are_stored_once() {
+        let mut replay = replay();
+        for _ in 0..3 {
+            replay.push(frame(16_666_666, 1.0));
+        }
+        replay.push(frame(16_666_666, 0.0));
+        replay.push(frame(8_333_333, 0.0));
+        assert_eq!(replay.frames.len(), 3);
+        assert_eq!(replay.len(), 5);
+        assert_eq!(replay.frames().nth(2), Some(frame(16_666_666, 1.0)));
+        assert_eq!(replay.frames().nth(4), Some(frame(8_333_333, 0.0)));
+    }
+
+    #[test]
+    fn replays_survive_a_round_trip_through_ron() {
+        let mut replay = replay();
+        replay.push(Frame {
+            nanos: 4_166_666,
+            input: PlayerInput {
+                direction: -1.0,
+                target: Some(-123.456_79),
+                pause: true,
+                any_key: false,
+            },
+        });
+        replay.push(frame(12_499_998, 0.1));
+        let text = ron::ser::to_string(&replay).unwrap();
+        assert_eq!(ron::de::from_str::<Replay>(&text).unwrap(), replay);
+    }
+
+    /// Times the game and the rest of the app have run.
+    #[derive(Default)]
+    struct Runs {
+        game: u32,
+        app: u32,
+    }
+
+    #[test]
+    fn fast_forwarding_runs_only_the_game_once_per_played_frame() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Playback>()
+            .init_resource::<Runs>()
+            .add_stage_after(
+                CoreStage::PreUpdate,
+                crate::GameSchedule,
+                Schedule::default()
+                    .with_run_criteria(IntoSystem::into_system(repeat_frames))
+                    .with_stage(
+                        crate::GameStage::Update,
+                        SystemStage::single(|mut runs: ResMut<Runs>| runs.game += 1),
+                    ),
+            )
+            .add_system(|mut runs: ResMut<Runs>| runs.app += 1);
+        app.update();
+        assert_eq!(app.world.resource::<Runs>().game, 1);
+
+        let mut playback = app.world.resource_mut::<Playback>();
+        playback.start(replay());
+        playback.next = Some(0);
+        playback.speed = SPEEDS[2];
+        app.update();
+        let runs = app.world.resource::<Runs>();
+        assert_eq!(runs.game, 1 + SPEEDS[2]);
+        assert_eq!(runs.app, 2);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::error::GameError;
+use crate::level::Level;
+use crate::locale::LocalizedText;
+use crate::replay::{Playback, Recorder, Replay};
+use crate::sprite_sheet::{GameSpriteSheet, SpriteSheet};
+use crate::state::GameState;
+use crate::theme::{ActiveTheme, ThemedBackdrop, ThemedText};
+use crate::transition::{TransitionOverlay, TransitionStyle, Transitions};
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy)]
+enum ReplayButton {
+    /// Plays the listed replay with this index.
+    Play(usize),
+    Back,
+}
+
+type ChangedButton = (Changed<Interaction>, With<Button>);
+
+/// The replays on offer, newest first.
+struct ListedReplays(Vec<Replay>);
+
+pub fn enter_system_set() -> SystemSet {
+    SystemSet::on_enter(GameState::Replays).with_system(setup_replay_menu)
+}
+
+pub fn update_system_set() -> SystemSet {
+    SystemSet::on_update(GameState::Replays).with_system(menu_update)
+}
+
+pub fn exit_system_set() -> SystemSet {
+    SystemSet::on_exit(GameState::Replays).with_system(teardown)
+}
+
+fn replay_label(replay: &Replay) -> LocalizedText {
+    let seconds = replay.duration().as_secs();
+    LocalizedText::new("replays.entry")
+        .with_arg("number", replay.level + 1)
+        .with_arg("points", replay.result.points)
+        .with_arg("time", format!("{}:{:02}", seconds / 60, seconds % 60))
+}
+
+pub fn setup_replay_menu(mut commands: Commands, theme: Res<ActiveTheme>, recorder: Res<Recorder>) {
+    let replays: Vec<Replay> = match &recorder.save_to {
+        Some(dir) => crate::replay::list(dir)
+            .into_iter()
+            .map(|(_, replay)| replay)
+            .collect(),
+        None => Vec::new(),
+    };
+    // entries without a button are plain text
+    let mut entries: Vec<(Option<ReplayButton>, LocalizedText)> = replays
+        .iter()
+        .enumerate()
+        .map(|(index, replay)| (Some(ReplayButton::Play(index)), replay_label(replay)))
+        .collect();
+    if replays.is_empty() {
+        entries.push((None, LocalizedText::new("replays.none")));
+    }
+    entries.push((Some(ReplayButton::Back), LocalizedText::new("menu.back")));
+    commands.insert_resource(ListedReplays(replays));
+
+    let text_style = theme.text_style(ThemedText::Body);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceAround,
+                ..Default::default()
+            },
+            color: UiColor(theme.theme.backdrop),
+            ..Default::default()
+        })
+        .insert(ThemedBackdrop)
+        .with_children(|grandparent| {
+            grandparent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.0), Val::Percent(80.0)),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceAround,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in entries {
+                        let text = TextBundle {
+                            text: Text::with_section(
+                                "",
+                                text_style.clone(),
+                                TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            style: Style {
+                                flex_grow: 0.0,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        };
+                        let button = match button {
+                            Some(button) => button,
+                            None => {
+                                parent
+                                    .spawn_bundle(text)
+                                    .insert(label)
+                                    .insert(ThemedText::Body);
+                                continue;
+                            }
+                        };
+                        parent
+                            .spawn_bundle(ButtonBundle::default())
+                            .insert(button)
+                            .with_children(|btn| {
+                                btn.spawn_bundle(text)
+                                    .insert(label)
+                                    .insert(ThemedText::Body);
+                            });
+                    }
+                });
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn menu_update(
+    mut transitions: ResMut<Transitions>,
+    mut playback: ResMut<Playback>,
+    mut error: ResMut<GameError>,
+    listed: Res<ListedReplays>,
+    levels: Res<Vec<Level>>,
+    sprite_sheet: Res<GameSpriteSheet>,
+    sheets: Res<Assets<SpriteSheet>>,
+    interaction_query: Query<(&Interaction, &ReplayButton), ChangedButton>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *button {
+            ReplayButton::Back => transitions.set(GameState::MainMenu, TransitionStyle::Fade),
+            // the level is built from the sprite sheet, so wait for it to load
+            ReplayButton::Play(_) if !sheets.contains(&sprite_sheet.0) => (),
+            ReplayButton::Play(index) => {
+                let replay = match listed.0.get(index) {
+                    Some(replay) => replay,
+                    None => continue,
+                };
+                match replay.check(&levels) {
+                    Ok(()) => {
+                        playback.start(replay.clone());
+                        transitions.set(GameState::Level, TransitionStyle::Wipe);
+                    }
+                    Err(message) => error.report(&mut transitions, message),
+                }
+            }
+        }
+    }
+}
+
+fn teardown(
+    mut commands: Commands,
+    menu_nodes: Query<(Entity, &Node), Without<TransitionOverlay>>,
+) {
+    commands.remove_resource::<ListedReplays>();
+    for (e, _n) in menu_nodes.iter() {
+        commands.entity(e).despawn();
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowMode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const RESOLUTIONS: &[(u32, u32)] = &[(1100, 720), (1280, 800), (1600, 900), (1920, 1080)];
+pub const LANGUAGES: &[&str] = &["en", "fr", "de"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DisplayMode {
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum VsyncMode {
+    Off,
+    On,
+    Mailbox,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ControlScheme {
+    ArrowKeys,
+    Wasd,
+    Mouse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub display_mode: DisplayMode,
+    pub resolution: (u32, u32),
+    pub vsync: VsyncMode,
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub control_scheme: ControlScheme,
+    pub screen_shake: f32,
+    /// Length of the freeze when a brick breaks, relative to the default.
+    pub hit_stop: f32,
+    /// How far the paddle squashes when the ball lands on it, relative to the default.
+    pub squash: f32,
+    /// Brightness of the flash over bricks that are hit.
+    pub flash: f32,
+    /// Whether brick debris, the ball trail and paddle sparks are drawn.
+    pub particles: bool,
+    pub language: String,
+    /// Name of the theme file in `assets/themes`, without the `.theme.ron` extension.
+    pub theme: String,
+    /// Length of animated screen transitions in seconds; zero switches instantly.
+    pub transition_duration: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            display_mode: DisplayMode::Windowed,
+            resolution: RESOLUTIONS[0],
+            vsync: VsyncMode::Mailbox,
+            master_volume: 1.0,
+            sfx_volume: 0.8,
+            music_volume: 0.6,
+            control_scheme: ControlScheme::ArrowKeys,
+            screen_shake: 1.0,
+            hit_stop: 1.0,
+            squash: 1.0,
+            flash: 1.0,
+            particles: true,
+            language: LANGUAGES[0].to_string(),
+            theme: "default".to_string(),
+            transition_duration: 0.5,
+        }
+    }
+}
+
+impl Settings {
+    /// Location of the settings file, e.g. `~/.config/bevy_blocks/settings.ron` on Linux.
+    pub fn config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("bevy_blocks").join("settings.ron"))
+    }
+
+    /// Reads the settings file, falling back to the defaults if it is missing or invalid.
+    pub fn load() -> Settings {
+        Settings::config_path()
+            .and_then(|path| std::fs::File::open(path).ok())
+            .and_then(|file| ron::de::from_reader(file).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Settings::config_path().ok_or("no config directory on this platform")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.display_mode {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::SizedFullscreen,
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        match self.vsync {
+            VsyncMode::Off => PresentMode::Immediate,
+            VsyncMode::On => PresentMode::Fifo,
+            VsyncMode::Mailbox => PresentMode::Mailbox,
+        }
+    }
+
+    pub fn window_descriptor(&self, title: String) -> WindowDescriptor {
+        WindowDescriptor {
+            title,
+            width: self.resolution.0 as f32,
+            height: self.resolution.1 as f32,
+            present_mode: self.present_mode(),
+            mode: self.window_mode(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Pushes window related settings to the primary window whenever they change.
+pub fn apply_settings_system(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        if window.mode() != settings.window_mode() {
+            window.set_mode(settings.window_mode());
+        }
+        if window.present_mode() != settings.present_mode() {
+            window.set_present_mode(settings.present_mode());
+        }
+        let (width, height) = settings.resolution;
+        if window.requested_width() != width as f32 || window.requested_height() != height as f32 {
+            window.set_resolution(width as f32, height as f32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let settings = Settings {
+            display_mode: DisplayMode::Fullscreen,
+            resolution: RESOLUTIONS[2],
+            control_scheme: ControlScheme::Mouse,
+            screen_shake: 0.5,
+            language: "fr".to_string(),
+            ..Default::default()
+        };
+        let text = ron::ser::to_string_pretty(&settings, ron::ser::PrettyConfig::new()).unwrap();
+        let read: Settings = ron::de::from_str(&text).unwrap();
+        assert_eq!(read, settings);
+    }
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let read: Settings = ron::de::from_str("(music_volume: 0.25, language: \"de\")").unwrap();
+        assert_eq!(
+            read,
+            Settings {
+                music_volume: 0.25,
+                language: "de".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn display_settings_map_to_the_window() {
+        let settings = Settings {
+            display_mode: DisplayMode::BorderlessFullscreen,
+            vsync: VsyncMode::Off,
+            resolution: (1280, 800),
+            ..Default::default()
+        };
+        let window = settings.window_descriptor("Blocks".to_string());
+        assert_eq!(window.mode, WindowMode::BorderlessFullscreen);
+        assert_eq!(window.present_mode, PresentMode::Immediate);
+        assert_eq!((window.width, window.height), (1280.0, 800.0));
+    }
+}
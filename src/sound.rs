@@ -0,0 +1,363 @@
+use bevy::audio::{Audio, AudioSource, PlaybackSettings};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::events::{BrickDestroyed, BrickHit, LevelCompleted, LifeLost, PaddleHit, WallHit};
+use crate::powerup::PowerUpCollected;
+use crate::settings::Settings;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundCategory {
+    /// The ball bouncing off things.
+    Impact,
+    /// Lives, level clears and power-ups.
+    Event,
+    Menu,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    PaddleHit,
+    WallBounce,
+    /// Played whenever the ball touches a brick.
+    BrickHit,
+    /// Played on top of `BrickHit` when the brick breaks.
+    BrickDestroyed,
+    LifeLost,
+    LevelCleared,
+    PowerUp,
+    MenuNavigate,
+    MenuSelect,
+}
+
+impl SoundEffect {
+    pub fn category(&self) -> SoundCategory {
+        match self {
+            SoundEffect::PaddleHit
+            | SoundEffect::WallBounce
+            | SoundEffect::BrickHit
+            | SoundEffect::BrickDestroyed => SoundCategory::Impact,
+            SoundEffect::LifeLost | SoundEffect::LevelCleared | SoundEffect::PowerUp => {
+                SoundCategory::Event
+            }
+            SoundEffect::MenuNavigate | SoundEffect::MenuSelect => SoundCategory::Menu,
+        }
+    }
+}
+
+/// Request to play a sound effect.
+///
+/// Gameplay code never sends these directly; the systems below translate gameplay events into
+/// them, and `play_sounds_system` is the only place that talks to the audio device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaySound(pub SoundEffect);
+
+/// Mixing settings, read from `assets/audio/sounds.ron`.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SoundConfig {
+    /// Largest relative change in playback speed, applied at random to avoid repetition.
+    pub pitch_variation: f32,
+    pub category_volume: HashMap<SoundCategory, f32>,
+    /// Files for each effect, relative to the assets folder; one is picked at random.
+    pub effects: HashMap<SoundEffect, Vec<String>>,
+}
+
+impl SoundConfig {
+    pub fn load() -> SoundConfig {
+        let path = "assets/audio/sounds.ron";
+        match std::fs::File::open(path) {
+            Ok(file) => ron::de::from_reader(file).unwrap_or_else(|e| {
+                warn!("Failed to parse {}: {}", path, e);
+                SoundConfig::default()
+            }),
+            Err(e) => {
+                warn!("Failed to open {}: {}", path, e);
+                SoundConfig::default()
+            }
+        }
+    }
+}
+
+/// Loaded sound files for each effect.
+pub struct SoundLibrary {
+    effects: HashMap<SoundEffect, Vec<Handle<AudioSource>>>,
+}
+
+impl FromWorld for SoundLibrary {
+    fn from_world(world: &mut World) -> SoundLibrary {
+        let config = world.resource::<SoundConfig>();
+        let asset_server = world.resource::<AssetServer>();
+        let effects = config
+            .effects
+            .iter()
+            .map(|(effect, paths)| {
+                let handles = paths.iter().map(|path| asset_server.load(path)).collect();
+                (*effect, handles)
+            })
+            .collect();
+        SoundLibrary { effects }
+    }
+}
+
+impl SoundLibrary {
+    /// Every loaded clip, whichever effect it belongs to.
+    pub fn handles(&self) -> impl Iterator<Item = &Handle<AudioSource>> {
+        self.effects.values().flatten()
+    }
+}
+
+/// The ball bouncing off things and bricks breaking.
+pub fn gameplay_sounds_system(
+    mut paddle_hits: EventReader<PaddleHit>,
+    mut wall_hits: EventReader<WallHit>,
+    mut brick_hits: EventReader<BrickHit>,
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for _ in paddle_hits.iter() {
+        sounds.send(PlaySound(SoundEffect::PaddleHit));
+    }
+    for _ in wall_hits.iter() {
+        sounds.send(PlaySound(SoundEffect::WallBounce));
+    }
+    for _ in brick_hits.iter() {
+        sounds.send(PlaySound(SoundEffect::BrickHit));
+    }
+    for _ in destroyed.iter() {
+        sounds.send(PlaySound(SoundEffect::BrickDestroyed));
+    }
+}
+
+/// Lost lives, level clears and power-ups.
+pub fn progress_sounds_system(
+    mut lives_lost: EventReader<LifeLost>,
+    mut completed: EventReader<LevelCompleted>,
+    mut collected: EventReader<PowerUpCollected>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for _ in lives_lost.iter() {
+        sounds.send(PlaySound(SoundEffect::LifeLost));
+    }
+    for _ in completed.iter() {
+        sounds.send(PlaySound(SoundEffect::LevelCleared));
+    }
+    for _ in collected.iter() {
+        sounds.send(PlaySound(SoundEffect::PowerUp));
+    }
+}
+
+/// Ticks when the pointer moves onto a button and clicks when one is pressed.
+pub fn menu_sounds_system(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for interaction in interaction_query.iter() {
+        match interaction {
+            Interaction::Hovered => sounds.send(PlaySound(SoundEffect::MenuNavigate)),
+            Interaction::Clicked => sounds.send(PlaySound(SoundEffect::MenuSelect)),
+            Interaction::None => (),
+        }
+    }
+}
+
+pub fn play_sounds_system(
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+    config: Res<SoundConfig>,
+    library: Res<SoundLibrary>,
+    playback: Res<crate::replay::Playback>,
+    attract: Res<crate::attract::Attract>,
+    mut sounds: EventReader<PlaySound>,
+) {
+    // the sounds of several frames at once would only be noise, and the demo behind the main
+    // menu plays silently
+    if playback.is_fast_forwarding() || attract.is_playing() {
+        sounds.iter().for_each(drop);
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for PlaySound(effect) in sounds.iter() {
+        let handles = match library.effects.get(effect) {
+            Some(handles) if !handles.is_empty() => handles,
+            _ => continue,
+        };
+        let handle = handles[rng.gen_range(0..handles.len())].clone();
+        let category_volume = config
+            .category_volume
+            .get(&effect.category())
+            .copied()
+            .unwrap_or(1.0);
+        let volume = settings.master_volume * settings.sfx_volume * category_volume;
+        if volume <= 0.0 {
+            continue;
+        }
+        let variation = config.pitch_variation;
+        let speed = if variation > 0.0 {
+            1.0 + rng.gen_range(-variation..variation)
+        } else {
+            1.0
+        };
+        audio.play_with_settings(
+            handle,
+            PlaybackSettings::ONCE.with_volume(volume).with_speed(speed),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::Events;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<PowerUpCollected>()
+            .add_event::<PlaySound>()
+            .add_system(gameplay_sounds_system)
+            .add_system(progress_sounds_system)
+            .add_system(menu_sounds_system);
+        crate::events::add_events(&mut app);
+        app
+    }
+
+    fn played(app: &App) -> Vec<SoundEffect> {
+        let events = app.world.resource::<Events<PlaySound>>();
+        events
+            .get_reader()
+            .iter(events)
+            .map(|PlaySound(effect)| *effect)
+            .collect()
+    }
+
+    #[test]
+    fn bounces_play_matching_sounds() {
+        let mut app = app();
+        app.world
+            .resource_mut::<Events<PaddleHit>>()
+            .send(PaddleHit {
+                position: Vec3::ZERO,
+                strength: 1.0,
+            });
+        app.world.resource_mut::<Events<WallHit>>().send(WallHit {
+            position: Vec3::ZERO,
+            strength: 1.0,
+        });
+        app.update();
+        assert_eq!(
+            played(&app),
+            vec![SoundEffect::PaddleHit, SoundEffect::WallBounce]
+        );
+    }
+
+    #[test]
+    fn a_brick_that_survives_only_plays_the_hit() {
+        let mut app = app();
+        let brick = app.world.spawn().id();
+        app.world.resource_mut::<Events<BrickHit>>().send(BrickHit {
+            brick,
+            position: Vec3::ZERO,
+            size: Vec2::ONE,
+            strength: 1.0,
+        });
+        app.update();
+        assert_eq!(played(&app), vec![SoundEffect::BrickHit]);
+    }
+
+    #[test]
+    fn breaking_a_brick_plays_hit_and_destruction() {
+        let mut app = app();
+        let brick = app.world.spawn().id();
+        app.world.resource_mut::<Events<BrickHit>>().send(BrickHit {
+            brick,
+            position: Vec3::ZERO,
+            size: Vec2::ONE,
+            strength: 1.0,
+        });
+        app.world
+            .resource_mut::<Events<BrickDestroyed>>()
+            .send(BrickDestroyed {
+                position: Vec3::ZERO,
+                size: Vec2::ONE,
+                sprite: None,
+            });
+        app.update();
+        assert_eq!(
+            played(&app),
+            vec![SoundEffect::BrickHit, SoundEffect::BrickDestroyed]
+        );
+    }
+
+    #[test]
+    fn progress_plays_event_sounds() {
+        let mut app = app();
+        app.world
+            .resource_mut::<Events<LifeLost>>()
+            .send(LifeLost { lives_left: 2 });
+        app.world
+            .resource_mut::<Events<LevelCompleted>>()
+            .send(LevelCompleted { level: 0 });
+        app.world
+            .resource_mut::<Events<PowerUpCollected>>()
+            .send(PowerUpCollected);
+        app.update();
+        let sounds = played(&app);
+        assert_eq!(
+            sounds,
+            vec![
+                SoundEffect::LifeLost,
+                SoundEffect::LevelCleared,
+                SoundEffect::PowerUp
+            ]
+        );
+        assert!(sounds
+            .iter()
+            .all(|sound| sound.category() == SoundCategory::Event));
+    }
+
+    #[test]
+    fn hovering_and_clicking_buttons_play_menu_sounds() {
+        let mut app = app();
+        let button = app
+            .world
+            .spawn()
+            .insert(Button)
+            .insert(Interaction::Hovered)
+            .id();
+        app.update();
+        assert_eq!(played(&app), vec![SoundEffect::MenuNavigate]);
+
+        *app.world.get_mut::<Interaction>(button).unwrap() = Interaction::Clicked;
+        app.update();
+        assert!(played(&app).contains(&SoundEffect::MenuSelect));
+    }
+
+    #[test]
+    fn nothing_plays_without_events() {
+        let mut app = app();
+        app.world.spawn().insert(Button).insert(Interaction::None);
+        app.update();
+        assert!(played(&app).is_empty());
+    }
+
+    #[test]
+    fn sound_config_lists_every_effect() {
+        let config: SoundConfig =
+            ron::de::from_reader(std::fs::File::open("assets/audio/sounds.ron").unwrap()).unwrap();
+        for effect in [
+            SoundEffect::PaddleHit,
+            SoundEffect::WallBounce,
+            SoundEffect::BrickHit,
+            SoundEffect::BrickDestroyed,
+            SoundEffect::LifeLost,
+            SoundEffect::LevelCleared,
+            SoundEffect::PowerUp,
+            SoundEffect::MenuNavigate,
+            SoundEffect::MenuSelect,
+        ] {
+            assert!(config.effects.contains_key(&effect), "{:?}", effect);
+        }
+    }
+}
@@ -1,42 +1,420 @@
-use bevy::prelude::*;
-use bevy::sprite::Rect;
+use bevy::{
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    sprite::{Anchor, Rect},
+};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default sprite sheet for the ball, paddle and bricks, relative to the assets folder.
+pub const SPRITE_SHEET_PATH: &str = "texture/blocks.sheet.ron";
+
+/// The area of a sprite that collides, centered on its pivot.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Hitbox {
+    Rect(f32, f32),
+    Circle(f32),
+}
+
+impl Hitbox {
+    /// Size of the box around the hitbox, which is what `Collider`s work with.
+    pub fn size(&self) -> Vec2 {
+        match *self {
+            Hitbox::Rect(width, height) => Vec2::new(width, height),
+            Hitbox::Circle(radius) => Vec2::splat(radius * 2.0),
+        }
+    }
+}
+
+/// One frame of an animation: the name of the sprite to show and for how many seconds.
+#[derive(Deserialize, Debug, Clone)]
+struct FrameSpecs {
+    sprite: String,
+    seconds: f32,
+}
 
 #[derive(Deserialize, Debug, Clone)]
 struct SpriteSpecs {
+    /// Name used to look the sprite up, e.g. `"paddle"`.
+    #[serde(default)]
+    name: String,
     x: usize,
     y: usize,
     width: usize,
     height: usize,
+    /// Point the sprite is positioned by, relative to its center, from `(-0.5, -0.5)` at the
+    /// bottom left to `(0.5, 0.5)` at the top right.
+    #[serde(default)]
+    pivot: Option<(f32, f32)>,
+    /// Collision shape; the whole sprite collides when this is not given.
+    #[serde(default)]
+    hitbox: Option<Hitbox>,
+    /// Frames played in a loop by sprites spawned from this entry.
+    #[serde(default)]
+    animation: Vec<FrameSpecs>,
 }
+
 #[derive(Deserialize, Debug, Clone)]
 struct SpriteSheetSpecs {
+    /// Path of the image, relative to the assets folder.
+    texture: String,
     texture_width: usize,
     texture_height: usize,
     sprites: Vec<SpriteSpecs>,
 }
-pub fn build_sprite_sheet(
-    asset_server: &mut Res<AssetServer>,
-    mut atlases: ResMut<Assets<TextureAtlas>>,
-) -> Handle<TextureAtlas> {
-    let specs: SpriteSheetSpecs =
-        ron::de::from_reader(std::fs::File::open("assets/texture/blocks-spritesheet.ron").unwrap())
-            .unwrap();
-
-    let atlas_image = asset_server.load("texture/blocks-spritesheet.png");
-    let mut texture_atlas = TextureAtlas::new_empty(
-        atlas_image,
-        Vec2::new(specs.texture_width as f32, specs.texture_height as f32),
-    );
-    for sprite in specs.sprites.iter() {
-        texture_atlas.add_texture(Rect {
-            min: Vec2::new(sprite.x as f32, sprite.y as f32),
-            max: Vec2::new(
-                (sprite.x + sprite.width) as f32,
-                (sprite.y + sprite.height) as f32,
+
+/// The sprite sheet's texture atlas together with the names, pivots and animations of its
+/// sprites.
+///
+/// Loaded from `.sheet.ron` files by [`SpriteSheetLoader`]; the atlas is a labeled sub-asset of
+/// the sheet, so it keeps its handle when the file is reloaded.
+#[derive(TypeUuid, Clone)]
+#[uuid = "9c1d7e42-3a5b-4f08-b6e1-2d8f0a7c5e93"]
+pub struct SpriteSheet {
+    pub atlas: Handle<TextureAtlas>,
+    names: HashMap<String, usize>,
+    sizes: Vec<Vec2>,
+    pivots: Vec<Option<Vec2>>,
+    hitboxes: Vec<Option<Hitbox>>,
+    animations: HashMap<usize, Vec<(usize, f32)>>,
+}
+
+impl SpriteSheet {
+    /// Reads the contents of a `.sheet.ron` file, returning the sheet, its atlas and the path
+    /// of its texture.
+    ///
+    /// Neither handle is filled in: the caller decides where the texture and atlas live and
+    /// points `atlas` and the atlas's `texture` at them.
+    pub fn from_ron(bytes: &[u8]) -> Result<(SpriteSheet, TextureAtlas, String), ron::Error> {
+        let specs: SpriteSheetSpecs = ron::de::from_bytes(bytes)?;
+        let mut texture_atlas = TextureAtlas::new_empty(
+            Handle::default(),
+            Vec2::new(specs.texture_width as f32, specs.texture_height as f32),
+        );
+        let mut names = HashMap::new();
+        let mut sizes = Vec::new();
+        let mut pivots = Vec::new();
+        let mut hitboxes = Vec::new();
+        for (index, sprite) in specs.sprites.iter().enumerate() {
+            texture_atlas.add_texture(Rect {
+                min: Vec2::new(sprite.x as f32, sprite.y as f32),
+                max: Vec2::new(
+                    (sprite.x + sprite.width) as f32,
+                    (sprite.y + sprite.height) as f32,
+                ),
+            });
+            if !sprite.name.is_empty() {
+                names.insert(sprite.name.clone(), index);
+            }
+            sizes.push(Vec2::new(sprite.width as f32, sprite.height as f32));
+            pivots.push(sprite.pivot.map(|(x, y)| Vec2::new(x, y)));
+            hitboxes.push(sprite.hitbox);
+        }
+        let mut animations = HashMap::new();
+        for (index, sprite) in specs.sprites.iter().enumerate() {
+            let frames: Vec<(usize, f32)> = sprite
+                .animation
+                .iter()
+                .filter_map(|frame| match names.get(&frame.sprite) {
+                    Some(frame_index) => Some((*frame_index, frame.seconds)),
+                    None => {
+                        warn!(
+                            "Animation of {:?} uses unknown sprite {:?}",
+                            sprite.name, frame.sprite
+                        );
+                        None
+                    }
+                })
+                .collect();
+            if !frames.is_empty() {
+                animations.insert(index, frames);
+            }
+        }
+        let sheet = SpriteSheet {
+            atlas: Handle::default(),
+            names,
+            sizes,
+            pivots,
+            hitboxes,
+            animations,
+        };
+        Ok((sheet, texture_atlas, specs.texture))
+    }
+
+    /// Atlas index of the sprite called `name`.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// A sprite showing the atlas entry at `index`, placed by its pivot. Animated entries start
+    /// on their first frame.
+    pub fn sprite_at(&self, index: usize) -> TextureAtlasSprite {
+        let first_frame = self
+            .animations
+            .get(&index)
+            .and_then(|frames| frames.first())
+            .map(|(frame, _)| *frame);
+        TextureAtlasSprite {
+            index: first_frame.unwrap_or(index),
+            anchor: match self.pivots.get(index).copied().flatten() {
+                Some(pivot) => Anchor::Custom(pivot),
+                None => Anchor::Center,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Atlas index of the sprite called `name`, or of the first entry if there is no such
+    /// sprite.
+    pub fn index_or_default(&self, name: &str) -> usize {
+        self.index(name).unwrap_or_else(|| {
+            warn!("No sprite named {:?} in the sprite sheet", name);
+            0
+        })
+    }
+
+    /// A sprite showing the entry called `name`, or the first entry if there is no such sprite.
+    pub fn sprite(&self, name: &str) -> TextureAtlasSprite {
+        self.sprite_at(self.index_or_default(name))
+    }
+
+    /// Size of the entry at `index` in the texture.
+    pub fn size(&self, index: usize) -> Vec2 {
+        self.sizes.get(index).copied().unwrap_or(Vec2::ZERO)
+    }
+
+    /// Collision shape of the entry at `index`, defaulting to a rectangle covering the sprite.
+    pub fn hitbox(&self, index: usize) -> Hitbox {
+        self.hitboxes
+            .get(index)
+            .copied()
+            .flatten()
+            .unwrap_or_else(|| {
+                let size = self.size(index);
+                Hitbox::Rect(size.x, size.y)
+            })
+    }
+
+    /// The animation of the entry at `index`, if it has one.
+    pub fn animation(&self, index: usize) -> Option<SpriteAnimation> {
+        self.animations
+            .get(&index)
+            .map(|frames| SpriteAnimation::new(frames.clone()))
+    }
+}
+
+/// Plays a looping sequence of atlas indices on a `TextureAtlasSprite`.
+#[derive(Component, Debug, Clone)]
+pub struct SpriteAnimation {
+    /// Atlas index and duration in seconds of each frame.
+    frames: Vec<(usize, f32)>,
+    current: usize,
+    timer: Timer,
+}
+
+impl SpriteAnimation {
+    fn new(frames: Vec<(usize, f32)>) -> SpriteAnimation {
+        let seconds = frames.first().map_or(0.0, |(_, seconds)| *seconds);
+        SpriteAnimation {
+            frames,
+            current: 0,
+            timer: Timer::from_seconds(seconds, false),
+        }
+    }
+
+    /// Plays `delta` of the animation, returning the atlas index to show if the frame changed.
+    fn advance(&mut self, delta: Duration) -> Option<usize> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        self.timer.tick(delta);
+        if !self.timer.finished() {
+            return None;
+        }
+        self.current = (self.current + 1) % self.frames.len();
+        let (index, seconds) = self.frames[self.current];
+        self.timer = Timer::from_seconds(seconds, false);
+        Some(index)
+    }
+}
+
+pub fn animate_sprites_system(
+    time: Res<Time>,
+    hit_stop: Res<crate::feel::HitStop>,
+    mut sprite_query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+) {
+    if hit_stop.is_frozen() {
+        return;
+    }
+    for (mut animation, mut sprite) in sprite_query.iter_mut() {
+        if let Some(index) = animation.advance(time.delta()) {
+            sprite.index = index;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SpriteSheetLoader;
+
+impl AssetLoader for SpriteSheetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let (mut sheet, mut texture_atlas, texture) = SpriteSheet::from_ron(bytes)?;
+            let texture_path = AssetPath::new(PathBuf::from(&texture), None);
+            texture_atlas.texture = load_context.get_handle(texture_path.clone());
+            sheet.atlas = load_context.set_labeled_asset("atlas", LoadedAsset::new(texture_atlas));
+            load_context.set_default_asset(LoadedAsset::new(sheet).with_dependency(texture_path));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sheet.ron"]
+    }
+}
+
+/// Handle to the game's sprite sheet, loaded once at startup from the path in `BlocksConfig`.
+pub struct GameSpriteSheet(pub Handle<SpriteSheet>);
+
+impl FromWorld for GameSpriteSheet {
+    fn from_world(world: &mut World) -> GameSpriteSheet {
+        let path = world.resource::<crate::BlocksConfig>().sprite_sheet.clone();
+        GameSpriteSheet(world.resource::<AssetServer>().load(&path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"(
+        texture: "texture/test.png",
+        texture_width: 100,
+        texture_height: 100,
+        sprites: [
+            SpriteSpecs(x: 0, y: 0, width: 10, height: 10),
+            SpriteSpecs(name: "red", x: 10, y: 0, width: 20, height: 10, pivot: Some((0.0, -0.5))),
+            SpriteSpecs(name: "blue", x: 30, y: 0, width: 20, height: 10),
+            SpriteSpecs(name: "ball", x: 50, y: 0, width: 12, height: 12, hitbox: Some(Circle(5.0))),
+            SpriteSpecs(
+                name: "flashing",
+                x: 10,
+                y: 0,
+                width: 20,
+                height: 10,
+                animation: [
+                    FrameSpecs(sprite: "red", seconds: 0.5),
+                    FrameSpecs(sprite: "blue", seconds: 0.25),
+                    FrameSpecs(sprite: "green", seconds: 1.0),
+                ],
             ),
-        });
+        ],
+    )"#;
+
+    fn sheet() -> SpriteSheet {
+        SpriteSheet::from_ron(SHEET.as_bytes()).unwrap().0
+    }
+
+    #[test]
+    fn sprites_are_found_by_name() {
+        let sheet = sheet();
+        assert_eq!(sheet.index("red"), Some(1));
+        assert_eq!(sheet.index("flashing"), Some(4));
+        assert_eq!(sheet.index("green"), None);
+        // unnamed entries can only be reached by index
+        assert_eq!(sheet.index(""), None);
+        assert_eq!(sheet.index_or_default("green"), 0);
+    }
+
+    #[test]
+    fn sprites_are_placed_by_their_pivot() {
+        let sheet = sheet();
+        let pivot = Vec2::new(0.0, -0.5);
+        assert!(matches!(sheet.sprite("red").anchor, Anchor::Custom(p) if p == pivot));
+        assert!(matches!(sheet.sprite("blue").anchor, Anchor::Center));
+    }
+
+    #[test]
+    fn animations_skip_unknown_frames_and_start_on_the_first() {
+        let sheet = sheet();
+        let flashing = sheet.index("flashing").unwrap();
+        assert_eq!(sheet.sprite_at(flashing).index, 1);
+        let animation = sheet.animation(flashing).unwrap();
+        assert_eq!(animation.frames, vec![(1, 0.5), (2, 0.25)]);
+        assert!(sheet.animation(sheet.index("red").unwrap()).is_none());
+    }
+
+    #[test]
+    fn animations_loop_through_their_frames() {
+        let sheet = sheet();
+        let mut animation = sheet.animation(sheet.index("flashing").unwrap()).unwrap();
+        let shown: Vec<Option<usize>> = (0..4)
+            .map(|_| animation.advance(Duration::from_millis(250)))
+            .collect();
+        assert_eq!(shown, vec![None, Some(2), Some(1), None]);
+    }
+
+    #[test]
+    fn the_loader_reads_the_sheet_and_its_atlas() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<TextureAtlas>()
+            .add_asset::<SpriteSheet>()
+            .init_asset_loader::<SpriteSheetLoader>();
+        let handle: Handle<SpriteSheet> =
+            app.world.resource::<AssetServer>().load(SPRITE_SHEET_PATH);
+        for _ in 0..500 {
+            if app
+                .world
+                .resource::<Assets<SpriteSheet>>()
+                .contains(&handle)
+            {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+            app.update();
+        }
+        let sheets = app.world.resource::<Assets<SpriteSheet>>();
+        let sheet = sheets.get(&handle).expect("Sprite sheet loaded");
+        let atlas = app
+            .world
+            .resource::<Assets<TextureAtlas>>()
+            .get(&sheet.atlas)
+            .expect("Atlas loaded with the sheet");
+        assert_eq!(atlas.textures.len(), sheet.sizes.len());
+        let paddle = sheet.index("paddle").expect("Sheet has a paddle");
+        assert_eq!(atlas.textures[paddle].size(), sheet.size(paddle));
+        assert_eq!(
+            atlas.texture,
+            app.world
+                .resource::<AssetServer>()
+                .get_handle("texture/blocks-spritesheet.png")
+        );
+    }
+
+    #[test]
+    fn hitboxes_become_collider_sizes() {
+        assert_eq!(Hitbox::Rect(80.0, 40.0).size(), Vec2::new(80.0, 40.0));
+        assert_eq!(Hitbox::Circle(15.0).size(), Vec2::new(30.0, 30.0));
+        let sheet = sheet();
+        let ball = sheet.index("ball").unwrap();
+        assert_eq!(sheet.hitbox(ball), Hitbox::Circle(5.0));
+        // without a hitbox the whole sprite collides
+        let red = sheet.index("red").unwrap();
+        assert_eq!(sheet.hitbox(red), Hitbox::Rect(20.0, 10.0));
+        assert_eq!(sheet.hitbox(99), Hitbox::Rect(0.0, 0.0));
     }
 
-    atlases.add(texture_atlas)
+    #[test]
+    fn broken_sheets_are_an_error() {
+        assert!(SpriteSheet::from_ron(b"(texture: \"a.png\")").is_err());
+    }
 }
@@ -1,8 +1,17 @@
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum GameState {
+    /// Waiting for assets at startup.
+    Loading,
+    /// Some assets failed to load; lists them.
+    LoadingFailed,
     MainMenu,
     PauseMenu,
     Level,
     GameOver,
+    Options,
+    /// Lists saved replays to play back.
+    Replays,
+    /// Something went wrong mid-game; shows what before going back to the main menu.
+    Error,
 }
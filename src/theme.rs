@@ -0,0 +1,305 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+use crate::settings::Settings;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ButtonColors {
+    pub normal: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+}
+
+/// Colors, fonts and text sizes shared by the menus and the HUD.
+///
+/// Themes are loaded from `assets/themes/<name>.theme.ron`; the one in use is picked by
+/// `Settings::theme`.
+#[derive(Deserialize, TypeUuid, Debug, Clone)]
+#[uuid = "5e3f1c2a-8d7b-4f6e-9a1c-3b2d4e5f6a7b"]
+pub struct Theme {
+    /// Clear color behind the playfield.
+    pub background: Color,
+    /// Color of the bars around the playfield when the window aspect does not match it.
+    pub letterbox: Color,
+    /// Full-screen panel behind the menus.
+    pub backdrop: Color,
+    pub text: Color,
+    /// Path of the font, relative to the assets folder.
+    pub font: String,
+    pub text_size: f32,
+    pub title_size: f32,
+    pub hud_size: f32,
+    pub small_size: f32,
+    pub button: ButtonColors,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            background: Color::rgb(0.58, 0.31, 0.15),
+            letterbox: Color::BLACK,
+            backdrop: Color::rgb(0.1, 0.1, 0.1),
+            text: Color::rgb(0.71, 0.8, 0.4),
+            font: "font/FiraSans-Light.ttf".to_string(),
+            text_size: 40.0,
+            title_size: 60.0,
+            hud_size: 40.0,
+            small_size: 30.0,
+            button: ButtonColors {
+                normal: Color::WHITE,
+                hovered: Color::rgb(0.85, 0.85, 0.85),
+                pressed: Color::rgb(0.7, 0.7, 0.7),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let theme: Theme = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(theme));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+/// Names of the themes found in `assets/themes`, sorted.
+pub fn available_themes() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir("assets/themes")
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .filter_map(|de| {
+                    de.file_name()
+                        .to_str()
+                        .and_then(|name| name.strip_suffix(".theme.ron"))
+                        .map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// The theme currently applied to the UI.
+///
+/// Holds the built-in default until the selected theme file has loaded.
+pub struct ActiveTheme {
+    /// Name of the selected theme file, without the `.theme.ron` extension.
+    pub selected: String,
+    pub handle: Handle<Theme>,
+    pub theme: Theme,
+    pub font: Handle<Font>,
+}
+
+impl ActiveTheme {
+    pub fn text_style(&self, role: ThemedText) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: match role {
+                ThemedText::Body => self.theme.text_size,
+                ThemedText::Title => self.theme.title_size,
+                ThemedText::Hud => self.theme.hud_size,
+                ThemedText::Small => self.theme.small_size,
+            },
+            color: self.theme.text,
+        }
+    }
+}
+
+impl FromWorld for ActiveTheme {
+    fn from_world(world: &mut World) -> ActiveTheme {
+        let selected = world.resource::<Settings>().theme.clone();
+        let asset_server = world.resource::<AssetServer>();
+        let theme = Theme::default();
+        ActiveTheme {
+            handle: asset_server.load(&format!("themes/{}.theme.ron", selected)),
+            font: asset_server.load(&theme.font),
+            selected,
+            theme,
+        }
+    }
+}
+
+/// Which of the theme's text sizes a piece of text uses.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum ThemedText {
+    Body,
+    Title,
+    Hud,
+    Small,
+}
+
+/// Marks a node whose color is the theme's menu backdrop.
+#[derive(Component)]
+pub struct ThemedBackdrop;
+
+/// Marks a sprite whose color is the theme's letterbox color.
+#[derive(Component)]
+pub struct ThemedLetterbox;
+
+/// Starts loading a new theme when the setting changes, and makes it active once loaded or
+/// whenever its file is modified.
+pub fn update_active_theme_system(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    themes: Res<Assets<Theme>>,
+    mut events: EventReader<AssetEvent<Theme>>,
+    mut active: ResMut<ActiveTheme>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if settings.theme != active.selected {
+        active.selected = settings.theme.clone();
+        active.handle = asset_server.load(&format!("themes/{}.theme.ron", settings.theme));
+    }
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                if *handle != active.handle {
+                    continue;
+                }
+                if let Some(theme) = themes.get(handle) {
+                    active.font = asset_server.load(&theme.font);
+                    active.theme = theme.clone();
+                    clear_color.0 = theme.background;
+                }
+            }
+            AssetEvent::Removed { .. } => (),
+        }
+    }
+}
+
+pub fn restyle_text_system(
+    active: Res<ActiveTheme>,
+    mut text_query: Query<(ChangeTrackers<ThemedText>, &ThemedText, &mut Text)>,
+) {
+    for (tracker, role, mut text) in text_query.iter_mut() {
+        if active.is_changed() || tracker.is_changed() {
+            let style = active.text_style(*role);
+            for section in text.sections.iter_mut() {
+                section.style = style.clone();
+            }
+        }
+    }
+}
+
+pub fn restyle_backdrop_system(
+    active: Res<ActiveTheme>,
+    mut backdrop_query: Query<(ChangeTrackers<ThemedBackdrop>, &mut UiColor)>,
+    mut letterbox_query: Query<&mut Sprite, With<ThemedLetterbox>>,
+) {
+    for (tracker, mut color) in backdrop_query.iter_mut() {
+        if active.is_changed() || tracker.is_changed() {
+            color.0 = active.theme.backdrop;
+        }
+    }
+    if active.is_changed() {
+        for mut sprite in letterbox_query.iter_mut() {
+            sprite.color = active.theme.letterbox;
+        }
+    }
+}
+
+/// Colors buttons according to their interaction state.
+pub fn restyle_button_system(
+    active: Res<ActiveTheme>,
+    mut button_query: Query<
+        (ChangeTrackers<Interaction>, &Interaction, &mut UiColor),
+        With<Button>,
+    >,
+) {
+    for (tracker, interaction, mut color) in button_query.iter_mut() {
+        if active.is_changed() || tracker.is_changed() {
+            color.0 = match interaction {
+                Interaction::Clicked => active.theme.button.pressed,
+                Interaction::Hovered => active.theme.button.hovered,
+                Interaction::None => active.theme.button.normal,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_shipped_theme_parses() {
+        let themes = available_themes();
+        assert!(themes.contains(&"default".to_string()));
+        for name in themes {
+            let path = format!("assets/themes/{}.theme.ron", name);
+            let bytes = std::fs::read(&path).unwrap();
+            if let Err(e) = ron::de::from_bytes::<Theme>(&bytes) {
+                panic!("{} does not parse: {}", path, e);
+            }
+        }
+    }
+
+    #[test]
+    fn text_roles_pick_their_size() {
+        let active = ActiveTheme {
+            selected: "default".to_string(),
+            handle: Handle::default(),
+            theme: Theme::default(),
+            font: Handle::default(),
+        };
+        let theme = &active.theme;
+        assert_eq!(
+            active.text_style(ThemedText::Body).font_size,
+            theme.text_size
+        );
+        assert_eq!(
+            active.text_style(ThemedText::Title).font_size,
+            theme.title_size
+        );
+        assert_eq!(active.text_style(ThemedText::Hud).font_size, theme.hud_size);
+        assert_eq!(
+            active.text_style(ThemedText::Small).font_size,
+            theme.small_size
+        );
+        assert_eq!(active.text_style(ThemedText::Body).color, theme.text);
+    }
+
+    #[test]
+    fn buttons_take_the_color_of_their_interaction() {
+        let mut app = App::new();
+        app.insert_resource(ActiveTheme {
+            selected: String::new(),
+            handle: Handle::default(),
+            theme: Theme::default(),
+            font: Handle::default(),
+        })
+        .add_system(restyle_button_system);
+        let button = app
+            .world
+            .spawn()
+            .insert(Button)
+            .insert(Interaction::Hovered)
+            .insert(UiColor::default())
+            .id();
+        app.update();
+        let hovered = Theme::default().button.hovered;
+        assert_eq!(app.world.get::<UiColor>(button).unwrap().0, hovered);
+        *app.world.get_mut::<Interaction>(button).unwrap() = Interaction::Clicked;
+        app.update();
+        let pressed = Theme::default().button.pressed;
+        assert_eq!(app.world.get::<UiColor>(button).unwrap().0, pressed);
+    }
+}
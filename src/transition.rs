@@ -0,0 +1,340 @@
+use bevy::{ecs::event::Events, input::keyboard::KeyboardInput, prelude::*, ui::FocusPolicy};
+use std::collections::VecDeque;
+
+use crate::clock::GameTime;
+use crate::settings::Settings;
+use crate::state::GameState;
+use crate::theme::ActiveTheme;
+
+/// Z given to the overlay so it is drawn above every other UI node.
+const OVERLAY_Z: f32 = 100.0;
+
+/// A change to the `GameState` stack, mirroring the methods of `State`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateChange {
+    Set(GameState),
+    Push(GameState),
+    Pop,
+    Restart,
+    /// Clears the whole stack, leaving only the given state.
+    Replace(GameState),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionStyle {
+    /// Switch instantly.
+    Cut,
+    /// Fade to the theme's backdrop color and back.
+    Fade,
+    /// Sweep a panel across the screen from left to right.
+    Wipe,
+    /// Slide a panel down over the screen and off the bottom.
+    Slide,
+}
+
+struct ActiveTransition {
+    /// Taken and applied to `State` once the screen is fully covered.
+    change: Option<StateChange>,
+    style: TransitionStyle,
+    timer: Timer,
+}
+
+/// Queue of pending state changes, played back one at a time behind an animated overlay.
+///
+/// Systems should request changes here instead of calling `State::set`, `push`, `pop`,
+/// `restart` or `replace` directly. The change itself is applied halfway through the
+/// animation, so the usual `on_exit`/`on_enter` systems run while the screen is covered.
+/// Player input is dropped for as long as a transition is playing.
+#[derive(Default)]
+pub struct Transitions {
+    queue: VecDeque<(StateChange, TransitionStyle)>,
+    active: Option<ActiveTransition>,
+}
+
+impl Transitions {
+    pub fn set(&mut self, state: GameState, style: TransitionStyle) {
+        self.request(StateChange::Set(state), style);
+    }
+
+    pub fn push(&mut self, state: GameState, style: TransitionStyle) {
+        self.request(StateChange::Push(state), style);
+    }
+
+    pub fn pop(&mut self, style: TransitionStyle) {
+        self.request(StateChange::Pop, style);
+    }
+
+    pub fn restart(&mut self, style: TransitionStyle) {
+        self.request(StateChange::Restart, style);
+    }
+
+    pub fn replace(&mut self, state: GameState, style: TransitionStyle) {
+        self.request(StateChange::Replace(state), style);
+    }
+
+    /// Queues a change, unless it repeats the change that would otherwise run last: the
+    /// back of the queue, or the playing change when nothing is queued.
+    pub fn request(&mut self, change: StateChange, style: TransitionStyle) {
+        let last = match self.queue.back() {
+            Some((queued, _)) => Some(queued),
+            None => self
+                .active
+                .as_ref()
+                .and_then(|active| active.change.as_ref()),
+        };
+        if last == Some(&change) {
+            return;
+        }
+        self.queue.push_back((change, style));
+    }
+
+    /// Whether a transition is playing or waiting to play.
+    pub fn is_busy(&self) -> bool {
+        self.active.is_some() || !self.queue.is_empty()
+    }
+}
+
+/// Label of [`run_transitions_system`], for systems that must see a change before it is
+/// applied.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TransitionSystem;
+
+/// The panel that covers the screen during a transition.
+#[derive(Component)]
+pub struct TransitionOverlay;
+
+fn apply_change(state: &mut State<GameState>, change: StateChange) {
+    let result = match change.clone() {
+        StateChange::Set(next) => state.set(next),
+        StateChange::Push(next) => state.push(next),
+        StateChange::Pop => state.pop(),
+        StateChange::Restart => state.restart(),
+        StateChange::Replace(next) => state.replace(next),
+    };
+    if let Err(e) = result {
+        warn!("Failed to apply {:?}: {:?}", change, e);
+    }
+}
+
+/// Starts queued transitions, applies their state change at the midpoint and animates the
+/// overlay.
+pub fn run_transitions_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    mut transitions: ResMut<Transitions>,
+    mut state: ResMut<State<GameState>>,
+    mut overlay_query: Query<(Entity, &mut Style, &mut UiColor), With<TransitionOverlay>>,
+) {
+    // a cut does not wait for the rest of an animation whose change is already applied
+    let revealing = matches!(&transitions.active, Some(active) if active.change.is_none());
+    if revealing && matches!(transitions.queue.front(), Some((_, TransitionStyle::Cut))) {
+        if let Some((change, _)) = transitions.queue.pop_front() {
+            apply_change(&mut state, change);
+        }
+    }
+    if transitions.active.is_none() {
+        let (change, style) = match transitions.queue.pop_front() {
+            Some(next) => next,
+            None => return,
+        };
+        if style == TransitionStyle::Cut || settings.transition_duration <= 0.0 {
+            apply_change(&mut state, change);
+            return;
+        }
+        transitions.active = Some(ActiveTransition {
+            change: Some(change),
+            style,
+            timer: Timer::from_seconds(settings.transition_duration, false),
+        });
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..Default::default()
+                },
+                color: UiColor(Color::NONE),
+                focus_policy: FocusPolicy::Block,
+                ..Default::default()
+            })
+            .insert(TransitionOverlay);
+        return;
+    }
+
+    let active = transitions.active.as_mut().unwrap();
+    active.timer.tick(time.delta());
+    let progress = active.timer.percent();
+    if progress >= 0.5 {
+        if let Some(change) = active.change.take() {
+            apply_change(&mut state, change);
+        }
+    }
+    // how much of the screen is covered, peaking at the midpoint
+    let cover = 1.0 - (progress * 2.0 - 1.0).abs();
+    let revealing = progress >= 0.5;
+    let style = active.style;
+    let finished = active.timer.finished();
+    for (entity, mut node_style, mut color) in overlay_query.iter_mut() {
+        if finished {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let backdrop = theme.theme.backdrop;
+        match style {
+            TransitionStyle::Fade => {
+                let mut faded = backdrop;
+                faded.set_a(cover);
+                color.0 = faded;
+            }
+            TransitionStyle::Wipe => {
+                color.0 = backdrop;
+                node_style.size.width = Val::Percent(cover * 100.0);
+                node_style.position.left = if revealing {
+                    Val::Percent((1.0 - cover) * 100.0)
+                } else {
+                    Val::Percent(0.0)
+                };
+            }
+            TransitionStyle::Slide => {
+                color.0 = backdrop;
+                let offset = (1.0 - cover) * 100.0;
+                node_style.position.top = if revealing {
+                    Val::Percent(offset)
+                } else {
+                    Val::Percent(-offset)
+                };
+            }
+            TransitionStyle::Cut => (),
+        }
+    }
+    if finished {
+        transitions.active = None;
+    }
+}
+
+/// Drops key presses and clicks while a transition is playing.
+///
+/// Keys held through a transition are left down rather than let go, so once it is over they
+/// do not count as pressed again, say the key that resumed a paused game.
+pub fn block_input_system(
+    transitions: Res<Transitions>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut mouse_buttons: ResMut<Input<MouseButton>>,
+) {
+    if transitions.active.is_none() {
+        return;
+    }
+    keyboard_events.clear();
+    keys.clear();
+    mouse_buttons.clear();
+}
+
+/// Lifts the overlay above the rest of the UI.
+///
+/// `ui_z_system` stacks root nodes in query order, so this runs after it, in `CoreStage::Last`,
+/// and patches the already propagated `GlobalTransform` as well.
+pub fn raise_overlay_system(
+    mut overlay_query: Query<(&mut Transform, &mut GlobalTransform), With<TransitionOverlay>>,
+) {
+    for (mut transform, mut global) in overlay_query.iter_mut() {
+        transform.translation.z = OVERLAY_Z;
+        global.translation.z = OVERLAY_Z;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A game on the main menu whose transitions take a second, stepped a tenth at a time.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state(GameState::MainMenu)
+            .insert_resource(Settings {
+                transition_duration: 1.0,
+                ..Default::default()
+            })
+            .insert_resource(ActiveTheme {
+                selected: String::new(),
+                handle: Handle::default(),
+                theme: Default::default(),
+                font: Handle::default(),
+            })
+            .insert_resource(GameTime::fixed(Duration::from_millis(100)))
+            .add_system_to_stage(CoreStage::First, crate::clock::update_game_time_system)
+            .init_resource::<Transitions>()
+            .add_system(run_transitions_system);
+        app
+    }
+
+    fn state(app: &App) -> GameState {
+        app.world.resource::<State<GameState>>().current().clone()
+    }
+
+    #[test]
+    fn repeated_requests_are_dropped() {
+        let mut transitions = Transitions::default();
+        transitions.push(GameState::PauseMenu, TransitionStyle::Fade);
+        transitions.push(GameState::PauseMenu, TransitionStyle::Cut);
+        transitions.pop(TransitionStyle::Fade);
+        assert_eq!(transitions.queue.len(), 2);
+    }
+
+    #[test]
+    fn a_change_can_be_requested_again_after_a_different_one() {
+        let mut transitions = Transitions::default();
+        transitions.push(GameState::PauseMenu, TransitionStyle::Cut);
+        transitions.pop(TransitionStyle::Cut);
+        transitions.push(GameState::PauseMenu, TransitionStyle::Cut);
+        assert_eq!(transitions.queue.len(), 3);
+    }
+
+    #[test]
+    fn requests_are_dropped_while_the_same_change_plays() {
+        let mut app = app();
+        let mut transitions = app.world.resource_mut::<Transitions>();
+        transitions.set(GameState::Options, TransitionStyle::Fade);
+        app.update();
+        let mut transitions = app.world.resource_mut::<Transitions>();
+        assert!(transitions.queue.is_empty());
+        transitions.set(GameState::Options, TransitionStyle::Fade);
+        assert!(transitions.queue.is_empty());
+    }
+
+    #[test]
+    fn the_state_changes_halfway_through() {
+        let mut app = app();
+        app.world
+            .resource_mut::<Transitions>()
+            .set(GameState::Options, TransitionStyle::Fade);
+        // the first frame starts the transition
+        app.update();
+        for _ in 0..4 {
+            app.update();
+            assert_eq!(state(&app), GameState::MainMenu);
+        }
+        app.update();
+        assert_eq!(state(&app), GameState::Options);
+        assert!(app.world.resource::<Transitions>().is_busy());
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(!app.world.resource::<Transitions>().is_busy());
+    }
+
+    #[test]
+    fn cuts_apply_at_once() {
+        let mut app = app();
+        app.world
+            .resource_mut::<Transitions>()
+            .set(GameState::Options, TransitionStyle::Cut);
+        app.update();
+        assert_eq!(state(&app), GameState::Options);
+        assert!(!app.world.resource::<Transitions>().is_busy());
+    }
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use bevy::{ecs::event::Events, input::keyboard::KeyboardInput, input::ElementState, prelude::*};
+use bevy_blocks::attract::{Attract, IDLE_SECONDS};
+use bevy_blocks::level::LevelSource;
+use bevy_blocks::replay::Recorder;
+use bevy_blocks::state::GameState;
+use bevy_blocks::transition::{TransitionStyle, Transitions};
+use bevy_blocks::BlocksConfig;
+
+use common::{brick_row, Harness};
+
+/// Most frames any one step of the demo is given.
+const MAX_TICKS: u32 = 600;
+
+/// A game left on the main menu. The demo is part of `CorePlugin`, so the harness already runs it.
+fn idle_menu() -> Harness {
+    let mut game = Harness::with_config(BlocksConfig {
+        levels: LevelSource::Levels(vec![brick_row(), brick_row()]),
+        seed: Some(0),
+        ..Default::default()
+    });
+    let mut transitions = game.app.world.resource_mut::<Transitions>();
+    transitions.pop(TransitionStyle::Cut);
+    transitions.set(GameState::MainMenu, TransitionStyle::Cut);
+    tick_until(&mut game, |game| game.state() == GameState::MainMenu);
+    // forget the game that was left for the menu
+    game.app.world.resource_mut::<Recorder>().last = None;
+    game
+}
+
+fn tick_until(game: &mut Harness, done: impl Fn(&Harness) -> bool) {
+    for _ in 0..MAX_TICKS {
+        if done(game) && !game.transitions_busy() {
+            return;
+        }
+        game.tick(&[]);
+    }
+    panic!("Gave up after {} frames in {:?}", MAX_TICKS, game.state());
+}
+
+#[test]
+fn idle_menu_plays_a_demo_until_a_key_is_pressed() {
+    let mut game = idle_menu();
+    let mut idle = game.app.world.resource_mut::<Attract>();
+    idle.tick(IDLE_SECONDS, false);
+    tick_until(&mut game, |game| game.state() == GameState::Level);
+    assert!(game.app.world.resource::<Attract>().is_playing());
+
+    // the AI carries on past the pause a new level starts with
+    let start = game.paddle_position();
+    game.run(120, &[]);
+    assert_eq!(game.state(), GameState::Level);
+    assert_ne!(game.paddle_position(), start);
+
+    game.app
+        .world
+        .resource_mut::<Events<KeyboardInput>>()
+        .send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::Space),
+            state: ElementState::Pressed,
+        });
+    tick_until(&mut game, |game| game.state() == GameState::MainMenu);
+    assert!(!game.app.world.resource::<Attract>().is_playing());
+    assert_eq!(game.score().points, 0);
+    assert_eq!(game.score().lives, 3);
+    assert!(game.app.world.resource::<Recorder>().last.is_none());
+}
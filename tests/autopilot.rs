@@ -0,0 +1,42 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_blocks::autopilot::AiDifficulty;
+use bevy_blocks::state::GameState;
+use bevy_blocks::BlocksConfig;
+
+use common::{Harness, TICK};
+
+fn autopilot_game(difficulty: AiDifficulty) -> Harness {
+    Harness::with_brick_row(BlocksConfig {
+        seed: Some(0),
+        autopilot: Some(difficulty),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn autopilot_keeps_the_ball_in_play() {
+    let mut game = autopilot_game(AiDifficulty::HARD);
+    game.resume();
+    let ticks = (30.0 / TICK.as_secs_f32()) as u32;
+    for _ in 0..ticks {
+        if game.state() != GameState::Level {
+            break;
+        }
+        game.tick(&[]);
+    }
+    assert_eq!(game.score().lives, 3);
+    assert!(game.score().points > 0);
+}
+
+#[test]
+fn held_keys_do_not_steer_the_autopilot() {
+    let mut steered = autopilot_game(AiDifficulty::HARD);
+    let mut left_alone = autopilot_game(AiDifficulty::HARD);
+    steered.resume();
+    left_alone.resume();
+    steered.run(60, &[KeyCode::Left]);
+    left_alone.run(60, &[]);
+    assert_eq!(steered.paddle_position(), left_alone.paddle_position());
+}
@@ -0,0 +1,220 @@
+//! Runs the game's systems headless, without a window or renderer, for tests.
+//!
+//! A [`Harness`] starts straight in a level loaded from a RON string, steps a fixed number of
+//! frames of exactly [`TICK`] each, and holds whatever keys the test asks for on every frame.
+
+// not every test file uses every helper
+#![allow(dead_code, unused_imports)]
+
+use bevy::{
+    ecs::event::Events,
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+
+use bevy_blocks::gameplay::{Ball, Collider, Paddle, Score};
+use bevy_blocks::level::{Level, LevelSource};
+use bevy_blocks::state::GameState;
+use bevy_blocks::transition::Transitions;
+use bevy_blocks::BlocksConfig;
+
+/// Length of every frame stepped by the harness.
+pub use bevy_blocks::headless::TICK;
+
+/// Most frames `resume` waits for the game to come back from the pause menu.
+const MAX_RESUME_TICKS: u32 = 600;
+
+/// A row of bricks from wall to wall, a level that takes a while to clear.
+pub const BRICK_ROW: &str = r#"Level(
+    name: "Brick Row",
+    level_width: 1100,
+    level_height: 720,
+    ball_velocity: 1.5,
+    block_margin: 0,
+    block_width: 100,
+    block_height: 40,
+    blocks: [Block(name: 'a', sprite: "brick_cyan")],
+    rows: ["aaaaaaaaaaa"],
+)"#;
+
+/// The [`BRICK_ROW`] level.
+pub fn brick_row() -> Level {
+    ron::de::from_str(BRICK_ROW).expect("Test level is valid RON")
+}
+
+pub struct Harness {
+    pub app: App,
+}
+
+impl Harness {
+    /// A game on the level described by `level`, paused as it starts like a real game.
+    pub fn new(level: &str) -> Harness {
+        Harness::with_levels(&[level])
+    }
+
+    /// A game that plays through `levels` in order.
+    pub fn with_levels(levels: &[&str]) -> Harness {
+        let levels: Vec<Level> = levels
+            .iter()
+            .map(|level| ron::de::from_str(level).expect("Test level is valid RON"))
+            .collect();
+        Harness::with_config(BlocksConfig {
+            levels: LevelSource::Levels(levels),
+            seed: Some(0),
+            ..Default::default()
+        })
+    }
+
+    /// A game set up by `config`, for tests that need more than levels.
+    pub fn with_config(config: BlocksConfig) -> Harness {
+        let app = bevy_blocks::headless::app(config).expect("Test game can be built");
+        let mut harness = Harness { app };
+        // the level asks for the pause menu on its first frame, which opens on the next
+        harness.tick(&[]);
+        while harness.transitions_busy() {
+            harness.tick(&[]);
+        }
+        harness
+    }
+
+    /// A game on the [`BRICK_ROW`] level, set up by `config` otherwise.
+    pub fn with_brick_row(config: BlocksConfig) -> Harness {
+        Harness::with_config(BlocksConfig {
+            levels: LevelSource::Levels(vec![brick_row()]),
+            ..config
+        })
+    }
+
+    /// Steps one frame while holding `keys`; any other key is released.
+    pub fn tick(&mut self, keys: &[KeyCode]) {
+        let mut input = self.app.world.resource_mut::<Input<KeyCode>>();
+        input.clear();
+        let released: Vec<KeyCode> = input
+            .get_pressed()
+            .filter(|key| !keys.contains(key))
+            .copied()
+            .collect();
+        for key in released.iter() {
+            input.release(*key);
+        }
+        let pressed: Vec<KeyCode> = keys
+            .iter()
+            .filter(|key| !input.pressed(**key))
+            .copied()
+            .collect();
+        for key in pressed.iter() {
+            input.press(*key);
+        }
+        let mut events = self.app.world.resource_mut::<Events<KeyboardInput>>();
+        let changes = pressed
+            .iter()
+            .map(|key| (*key, ElementState::Pressed))
+            .chain(released.iter().map(|key| (*key, ElementState::Released)));
+        for (key, state) in changes {
+            events.send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+        }
+        self.app.update();
+    }
+
+    /// Steps `ticks` frames while holding `keys`.
+    pub fn run(&mut self, ticks: u32, keys: &[KeyCode]) {
+        for _ in 0..ticks {
+            self.tick(keys);
+        }
+    }
+
+    /// Plays a script of `(frames, keys held)` steps, one after the other.
+    pub fn run_script(&mut self, script: &[(u32, &[KeyCode])]) {
+        for (ticks, keys) in script {
+            self.run(*ticks, keys);
+        }
+    }
+
+    /// Dismisses the pause menu and steps until play has resumed.
+    pub fn resume(&mut self) {
+        assert_eq!(self.state(), GameState::PauseMenu, "Game is not paused");
+        self.tick(&[KeyCode::Return]);
+        for _ in 0..MAX_RESUME_TICKS {
+            if self.state() == GameState::Level && !self.transitions_busy() {
+                return;
+            }
+            self.tick(&[]);
+        }
+        panic!("Game did not resume within {} frames", MAX_RESUME_TICKS);
+    }
+
+    /// Moves the ball to `position` and sends it off with `velocity`, in units per second.
+    pub fn aim_ball(&mut self, position: Vec2, velocity: Vec2) {
+        let mut query = self.app.world.query::<(&mut Ball, &mut Transform)>();
+        let (mut ball, mut transform) = query
+            .iter_mut(&mut self.app.world)
+            .next()
+            .expect("Level has a ball");
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        ball.velocity = velocity.extend(0.0);
+    }
+
+    pub fn ball_velocity(&mut self) -> Vec2 {
+        let mut query = self.app.world.query::<&Ball>();
+        query
+            .iter(&self.app.world)
+            .next()
+            .expect("Level has a ball")
+            .velocity
+            .truncate()
+    }
+
+    pub fn score(&self) -> &Score {
+        self.app.world.resource::<Score>()
+    }
+
+    pub fn score_mut(&mut self) -> Mut<'_, Score> {
+        self.app.world.resource_mut::<Score>()
+    }
+
+    pub fn state(&self) -> GameState {
+        self.app
+            .world
+            .resource::<State<GameState>>()
+            .current()
+            .clone()
+    }
+
+    pub fn transitions_busy(&self) -> bool {
+        self.app.world.resource::<Transitions>().is_busy()
+    }
+
+    /// Number of entities with a `T`.
+    pub fn count<T: Component>(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<T>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    /// Number of bricks left standing.
+    pub fn bricks(&mut self) -> usize {
+        self.app
+            .world
+            .query::<&Collider>()
+            .iter(&self.app.world)
+            .filter(|collider| matches!(collider, Collider::Scorable { .. }))
+            .count()
+    }
+
+    pub fn paddle_position(&mut self) -> Vec2 {
+        let mut query = self.app.world.query_filtered::<&Transform, With<Paddle>>();
+        query
+            .iter(&self.app.world)
+            .next()
+            .expect("Level has a paddle")
+            .translation
+            .truncate()
+    }
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_blocks::feel::HitStop;
+use bevy_blocks::gameplay::{Ball, Paddle};
+use bevy_blocks::state::GameState;
+
+use common::{Harness, TICK};
+
+/// A single brick straight above the ball's serving position.
+const ONE_BRICK: &str = r#"Level(
+    name: "One Brick",
+    level_width: 1100,
+    level_height: 720,
+    ball_velocity: 1.5,
+    block_margin: 0,
+    block_width: 100,
+    block_height: 40,
+    blocks: [Block(name: 'a', sprite: "brick_cyan")],
+    rows: ["     a"],
+)"#;
+
+const TWO_BRICKS: &str = r#"Level(
+    level_width: 1100,
+    level_height: 720,
+    ball_velocity: 1.5,
+    block_margin: 0,
+    block_width: 100,
+    block_height: 40,
+    blocks: [Block(name: 'a', sprite: "brick_cyan")],
+    rows: ["a         a"],
+)"#;
+
+/// One brick that takes two hits, straight above the ball's serving position.
+const TOUGH_BRICK: &str = r#"Level(
+    level_width: 1100,
+    level_height: 720,
+    ball_velocity: 1.5,
+    block_margin: 0,
+    block_width: 100,
+    block_height: 40,
+    blocks: [Block(name: 'a', sprite: "brick_cyan", hits: 2)],
+    rows: ["     a"],
+)"#;
+
+/// Frames in `seconds` of play.
+fn ticks(seconds: f32) -> u32 {
+    (seconds / TICK.as_secs_f32()).ceil() as u32
+}
+
+#[test]
+fn a_new_level_starts_paused() {
+    let mut game = Harness::new(ONE_BRICK);
+    assert_eq!(game.state(), GameState::PauseMenu);
+    assert_eq!(game.bricks(), 1);
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.count::<Paddle>(), 1);
+    assert_eq!(game.score().bricks_left, 1);
+    game.resume();
+    assert_eq!(game.state(), GameState::Level);
+}
+
+#[test]
+fn ball_aimed_straight_up_clears_a_one_brick_level() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.resume();
+    game.aim_ball(Vec2::new(0.0, -250.0), Vec2::new(0.0, 400.0));
+    game.run(ticks(3.0), &[]);
+    assert_eq!(game.score().points, 1);
+    assert_eq!(game.score().bricks_left, 0);
+    assert_eq!(game.score().current_level, 1);
+    // that was the only level
+    assert_eq!(game.state(), GameState::GameOver);
+    assert_eq!(game.count::<Ball>(), 0);
+}
+
+#[test]
+fn a_tough_brick_survives_its_first_hit() {
+    let mut game = Harness::new(TOUGH_BRICK);
+    game.resume();
+    game.aim_ball(Vec2::new(0.0, -250.0), Vec2::new(0.0, 400.0));
+    game.run(ticks(2.0), &[]);
+    assert_eq!(game.bricks(), 1);
+    assert_eq!(game.score().points, 0);
+    assert!(
+        game.ball_velocity().y < 0.0,
+        "The ball bounced off the brick"
+    );
+
+    // sent straight back up, it breaks the brick
+    game.aim_ball(Vec2::new(0.0, -250.0), Vec2::new(0.0, 400.0));
+    game.run(ticks(2.0), &[]);
+    assert_eq!(game.score().points, 1);
+    assert_eq!(game.score().bricks_left, 0);
+}
+
+#[test]
+fn clearing_a_level_starts_the_next_one() {
+    let mut game = Harness::with_levels(&[ONE_BRICK, TWO_BRICKS]);
+    game.resume();
+    game.aim_ball(Vec2::new(0.0, -250.0), Vec2::new(0.0, 400.0));
+    game.run(ticks(3.0), &[]);
+    assert_eq!(game.score().current_level, 1);
+    assert_eq!(game.state(), GameState::PauseMenu);
+    assert_eq!(game.bricks(), 2);
+    assert_eq!(game.score().bricks_left, 2);
+    assert_eq!(game.count::<Ball>(), 1);
+}
+
+#[test]
+fn ball_through_the_bottom_decrements_lives() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.resume();
+    game.aim_ball(Vec2::new(400.0, -250.0), Vec2::new(0.0, -400.0));
+    game.run(ticks(1.5), &[]);
+    assert_eq!(game.score().lives, 2);
+    assert_eq!(game.score().points, 0);
+    assert_eq!(game.state(), GameState::PauseMenu);
+    // the ball is served again once play resumes
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.bricks(), 1);
+}
+
+#[test]
+fn losing_the_ball_ends_the_combo_the_same_frame() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.resume();
+    game.score_mut().combo = 5;
+    game.aim_ball(Vec2::new(400.0, -250.0), Vec2::new(0.0, -400.0));
+    for _ in 0..ticks(1.5) {
+        game.tick(&[]);
+        if game.score().lives < 3 {
+            assert_eq!(game.score().combo, 0);
+            return;
+        }
+    }
+    panic!("The ball was never lost");
+}
+
+#[test]
+fn losing_the_last_life_returns_to_the_main_menu() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.score_mut().lives = 1;
+    game.resume();
+    game.aim_ball(Vec2::new(400.0, -250.0), Vec2::new(0.0, -400.0));
+    game.run(ticks(1.5), &[]);
+    assert_eq!(game.score().lives, 0);
+    assert_eq!(game.state(), GameState::MainMenu);
+    assert_eq!(game.count::<Ball>(), 0);
+    assert_eq!(game.bricks(), 0);
+}
+
+#[test]
+fn holding_the_pause_key_through_a_resume_keeps_playing() {
+    let mut game = Harness::new(ONE_BRICK);
+    assert_eq!(game.state(), GameState::PauseMenu);
+    // the key that leaves the pause menu stays down once play has resumed
+    for _ in 0..ticks(2.0) {
+        game.tick(&[KeyCode::Space]);
+        if game.state() == GameState::Level && !game.transitions_busy() {
+            break;
+        }
+    }
+    game.run(10, &[KeyCode::Space]);
+    assert_eq!(game.state(), GameState::Level);
+    assert!(!game.transitions_busy());
+}
+
+#[test]
+fn paddle_follows_scripted_input() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.resume();
+    let start = game.paddle_position();
+    game.run_script(&[(10, &[KeyCode::Left]), (30, &[KeyCode::Right]), (10, &[])]);
+    let moved = game.paddle_position().x - start.x;
+    // 20 frames' worth of movement at 500 units per second
+    assert!((moved - 500.0 * 20.0 * TICK.as_secs_f32()).abs() < 0.01);
+}
+
+#[test]
+fn ball_bouncing_off_the_paddle_keeps_the_life() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.resume();
+    game.aim_ball(Vec2::new(0.0, -250.0), Vec2::new(0.0, -400.0));
+    game.run(ticks(0.5), &[]);
+    assert_eq!(game.score().lives, 3);
+    assert_eq!(game.state(), GameState::Level);
+}
+
+#[test]
+fn losing_the_paddle_shows_the_error_screen() {
+    let mut game = Harness::new(ONE_BRICK);
+    game.resume();
+    let paddle = game
+        .app
+        .world
+        .query_filtered::<Entity, With<Paddle>>()
+        .iter(&game.app.world)
+        .next()
+        .expect("Level has a paddle");
+    game.app.world.despawn(paddle);
+    game.run(ticks(1.0), &[KeyCode::Left]);
+    assert_eq!(game.state(), GameState::Error);
+    assert_eq!(game.count::<Ball>(), 0);
+}
+
+/// Where the ball is in the game, and where it was last drawn.
+fn ball_position(game: &mut Harness) -> (Vec3, Vec3) {
+    let mut query = game
+        .app
+        .world
+        .query_filtered::<(&Transform, &GlobalTransform), With<Ball>>();
+    let (transform, global) = query
+        .iter(&game.app.world)
+        .next()
+        .expect("Level has a ball");
+    (transform.translation, global.translation)
+}
+
+#[test]
+fn hit_stop_holds_the_picture_while_the_ball_moves_on() {
+    let mut game = Harness::new(TWO_BRICKS);
+    game.app
+        .add_plugin(bevy::transform::TransformPlugin)
+        .add_plugin(bevy_blocks::feel::FeelPlugin);
+    // long enough that the freeze outlasts the test on a slow machine
+    game.app
+        .world
+        .resource_mut::<bevy_blocks::settings::Settings>()
+        .hit_stop = 100.0;
+    game.resume();
+    game.aim_ball(Vec2::new(-500.0, -250.0), Vec2::new(0.0, 400.0));
+    for _ in 0..ticks(1.5) {
+        // the freeze starts on the frame the brick breaks, or the next one
+        if game.app.world.resource::<HitStop>().is_frozen() {
+            break;
+        }
+        game.tick(&[]);
+    }
+    assert_eq!(game.score().points, 1, "The ball broke a brick");
+    assert!(game.app.world.resource::<HitStop>().is_frozen());
+
+    let (moved_from, drawn_at) = ball_position(&mut game);
+    game.tick(&[]);
+    let (moved_to, drawn) = ball_position(&mut game);
+    assert_ne!(moved_to, moved_from, "The game keeps playing");
+    assert_eq!(drawn, drawn_at, "The picture holds");
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_blocks::level::LevelSource;
+use bevy_blocks::replay::{self, FinalScore, Recorder};
+use bevy_blocks::state::GameState;
+use bevy_blocks::BlocksConfig;
+
+use common::{brick_row, Harness, TICK};
+
+/// Longest a game is given to end.
+const MAX_SECONDS: f32 = 600.0;
+
+#[test]
+fn a_played_back_game_ends_with_the_recorded_score() {
+    let mut game = Harness::with_brick_row(BlocksConfig {
+        seed: Some(0),
+        ..Default::default()
+    });
+    game.resume();
+    let max_ticks = (MAX_SECONDS / TICK.as_secs_f32()) as u32;
+    let mut ticks = 0;
+    // wander left, pause once, then wait at the right wall until out of lives, tapping a key
+    // whenever the pause menu shows
+    while matches!(game.state(), GameState::Level | GameState::PauseMenu) {
+        assert!(
+            ticks < max_ticks,
+            "Game did not end within {} frames",
+            max_ticks
+        );
+        let paused = game.state() == GameState::PauseMenu;
+        let keys: &[KeyCode] = match ticks {
+            _ if paused && ticks % 2 == 0 => &[KeyCode::Return],
+            _ if paused => &[],
+            0..=59 => &[KeyCode::Left],
+            120 => &[KeyCode::P],
+            _ => &[KeyCode::Right],
+        };
+        game.tick(keys);
+        ticks += 1;
+    }
+
+    let recorded = game
+        .app
+        .world
+        .resource::<Recorder>()
+        .last
+        .clone()
+        .expect("Finished game was recorded");
+    assert_eq!(recorded.result, FinalScore::from(game.score()));
+    assert!(!recorded.is_empty());
+    assert!(
+        recorded.result.points > 0,
+        "Game scored something worth checking"
+    );
+
+    let config = BlocksConfig {
+        levels: LevelSource::Levels(vec![brick_row()]),
+        ..Default::default()
+    };
+    let result = replay::play_headless(&recorded, config).expect("Replay plays back");
+    assert_eq!(result, recorded.result);
+}